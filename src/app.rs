@@ -7,20 +7,28 @@ use tui::backend::Backend;
 use tui::layout::Rect;
 use tui::Frame;
 
+use crate::components::blame_popup::BlamePopup;
 use crate::components::branch_popup::BranchPopup;
 use crate::components::branches::BranchComponent;
 use crate::components::cherry_pick_popup::CherryPickPopup;
 use crate::components::commit_popup::CommitPopup;
+use crate::components::confirm_popup::ConfirmPopup;
+use crate::components::conflict_popup::ConflictPopup;
 use crate::components::diff::DiffComponent;
 use crate::components::error_popup::ErrorComponent;
 use crate::components::files::FileComponent;
+use crate::components::hook_popup::HookPopup;
+use crate::components::identity_popup::IdentityPopup;
 use crate::components::log::LogComponent;
 use crate::components::log_popup::LogPopup;
 use crate::components::message_popup::MessagePopup;
 use crate::components::remote_popup::RemotePopupComponent;
+use crate::components::stash::StashComponent;
 use crate::components::status::StatusComponent;
 use crate::components::{Component, ComponentType};
 use crate::git::diff::DiffComponentType;
+use crate::git::log::{Commit, PathFilter};
+use crate::git::sign::SignatureStatus;
 use crate::{Event, InputLock};
 
 pub enum ProgramEvent {
@@ -37,24 +45,43 @@ pub enum GitEvent {
     PushSuccess,
     RefreshCommitLog,
     RefreshBranchList,
+    /// The name of a git hook and a line of its output.
+    HookOutput(String, String),
+    SignaturesVerified(Vec<(String, SignatureStatus)>),
+    /// A page of the log: its generation, the commits and whether the
+    /// history is finished.
+    CommitsLoaded(usize, Vec<Commit>, bool),
+    /// Commits found by a log search: its generation, the commits and
+    /// whether the search is finished.
+    SearchResults(usize, Vec<Commit>, bool),
+    /// The message to start a commit with: whether it amends HEAD, the
+    /// message and a warning when HEAD is already pushed.
+    MessagePrepared(bool, String, Option<String>),
 }
 
 pub struct App {
+    pub blame_popup: BlamePopup,
     pub branches: BranchComponent,
     pub branch_popup: BranchPopup,
     pub cherry_pick_popup: CherryPickPopup,
     pub commit_popup: CommitPopup,
+    pub confirm_popup: ConfirmPopup,
+    pub conflict_popup: ConflictPopup,
     pub diff: DiffComponent,
     pub diff_staged: DiffComponent,
     pub error_popup: ErrorComponent,
     pub event_sender: Sender<ProgramEvent>,
     pub files: FileComponent,
     pub focused_component: ComponentType,
+    pub hook_popup: HookPopup,
+    pub identity_popup: IdentityPopup,
     pub logs: LogComponent,
     pub log_popup: LogPopup,
     pub message_popup: MessagePopup,
     pub remote_popup: RemotePopupComponent,
+    pub stash: StashComponent,
     pub status: StatusComponent,
+    #[allow(dead_code)]
     pub repo_path: PathBuf,
 }
 
@@ -65,20 +92,34 @@ impl App {
         input_lock: InputLock,
     ) -> Self {
         Self {
+            blame_popup: BlamePopup::new(repo_path.clone(), event_sender.clone()),
             branches: BranchComponent::new(repo_path.clone(), event_sender.clone()),
             branch_popup: BranchPopup::new(repo_path.clone(), event_sender.clone()),
             cherry_pick_popup: CherryPickPopup::new(repo_path.clone(), event_sender.clone()),
             commit_popup: CommitPopup::new(repo_path.clone(), event_sender.clone()),
-            diff: DiffComponent::new(repo_path.clone(), DiffComponentType::Diff),
-            diff_staged: DiffComponent::new(repo_path.clone(), DiffComponentType::Staged),
+            confirm_popup: ConfirmPopup::new(repo_path.clone(), event_sender.clone()),
+            conflict_popup: ConflictPopup::new(repo_path.clone(), event_sender.clone()),
+            diff: DiffComponent::new(
+                repo_path.clone(),
+                DiffComponentType::Diff,
+                event_sender.clone(),
+            ),
+            diff_staged: DiffComponent::new(
+                repo_path.clone(),
+                DiffComponentType::Staged,
+                event_sender.clone(),
+            ),
             error_popup: ErrorComponent::new(event_sender.clone()),
             event_sender: event_sender.clone(),
             files: FileComponent::new(repo_path.clone(), event_sender.clone(), input_lock),
             focused_component: ComponentType::None,
+            hook_popup: HookPopup::new(event_sender.clone()),
+            identity_popup: IdentityPopup::new(repo_path.clone(), event_sender.clone()),
             logs: LogComponent::new(repo_path.clone(), event_sender.clone()),
-            log_popup: LogPopup::new(event_sender.clone()),
+            log_popup: LogPopup::new(repo_path.clone(), event_sender.clone()),
             message_popup: MessagePopup::new(),
             remote_popup: RemotePopupComponent::new(repo_path.clone(), event_sender.clone()),
+            stash: StashComponent::new(repo_path.clone(), event_sender.clone()),
             status: StatusComponent::new(repo_path.clone()),
             repo_path,
         }
@@ -86,8 +127,13 @@ impl App {
 
     pub fn is_popup_visible(&self) -> bool {
         self.commit_popup.visible()
+            || self.blame_popup.visible()
             || self.cherry_pick_popup.visible()
+            || self.confirm_popup.visible()
+            || self.conflict_popup.visible()
             || self.error_popup.visible()
+            || self.hook_popup.visible()
+            || self.identity_popup.visible()
             || self.branch_popup.visible()
             || self.message_popup.visible()
             || self.log_popup.visible()
@@ -97,10 +143,17 @@ impl App {
     pub fn draw_popup<B: Backend>(&mut self, f: &mut Frame<B>, size: Rect) -> Result<()> {
         match self.focused_component {
             ComponentType::BranchPopupComponent => self.branch_popup.draw(f, size)?,
-            ComponentType::CommitComponent => self.commit_popup.draw(f, size)?,
+            ComponentType::CommitComponent | ComponentType::AmendComponent => {
+                self.commit_popup.draw(f, size)?
+            }
+            ComponentType::ConflictComponent => self.conflict_popup.draw(f, size)?,
             ComponentType::ErrorComponent => self.error_popup.draw(f, size)?,
+            ComponentType::HookComponent => self.hook_popup.draw(f, size)?,
+            ComponentType::IdentityComponent => self.identity_popup.draw(f, size)?,
             ComponentType::RemotePopupComponent => self.remote_popup.draw(f, size),
+            ComponentType::BlameComponent(_) => self.blame_popup.draw(f, size)?,
             ComponentType::CherryPickPopup(_) => self.cherry_pick_popup.draw(f, size)?,
+            ComponentType::ConfirmComponent(_) => self.confirm_popup.draw(f, size)?,
             ComponentType::FullLogComponent(_) => self.log_popup.draw(f, size)?,
             ComponentType::MessageComponent(_) => self.message_popup.draw(f, size)?,
             _ => unreachable!(),
@@ -113,6 +166,7 @@ impl App {
         self.diff.update()?;
         self.diff_staged.update()?;
         self.logs.update()?;
+        self.stash.update()?;
         self.status.update()?;
         self.files.update()?;
         Ok(())
@@ -131,7 +185,8 @@ impl App {
                 KeyCode::Char('5') => {
                     self.focus(ComponentType::DiffComponent(DiffComponentType::Staged))
                 }
-                KeyCode::Esc if !self.is_popup_visible() => self
+                KeyCode::Char('6') => self.focus(ComponentType::StashComponent),
+                KeyCode::Esc if !self.is_popup_visible() && !self.logs.is_editing() => self
                     .event_sender
                     .send(ProgramEvent::Exit)
                     .expect("Send failed"),
@@ -145,18 +200,30 @@ impl App {
 
     fn handle_input(&mut self, ev: KeyEvent) -> Result<()> {
         match &mut self.focused_component {
-            ComponentType::LogComponent => self.logs.handle_event(ev)?,
+            ComponentType::LogComponent | ComponentType::PathLogComponent(_) => {
+                self.logs.handle_event(ev)?
+            }
             ComponentType::ErrorComponent => self.error_popup.handle_event(ev)?,
+            ComponentType::HookComponent => self.hook_popup.handle_event(ev)?,
+            ComponentType::IdentityComponent => self.identity_popup.handle_event(ev)?,
             ComponentType::BranchComponent => self.branches.handle_event(ev)?,
             ComponentType::FilesComponent => self.files.handle_event(ev)?,
-            ComponentType::CommitComponent => self.commit_popup.handle_event(ev)?,
+            ComponentType::CommitComponent | ComponentType::AmendComponent => {
+                self.commit_popup.handle_event(ev)?
+            }
+            ComponentType::ConflictComponent => self.conflict_popup.handle_event(ev)?,
             ComponentType::BranchPopupComponent => self.branch_popup.handle_event(ev)?,
             ComponentType::RemotePopupComponent => self.remote_popup.handle_event(ev)?,
+            ComponentType::StashComponent => self.stash.handle_event(ev)?,
+            ComponentType::BlameComponent(_) => self.blame_popup.handle_event(ev)?,
             ComponentType::CherryPickPopup(_) => self.cherry_pick_popup.handle_event(ev)?,
+            ComponentType::ConfirmComponent(_) => self.confirm_popup.handle_event(ev)?,
             ComponentType::MessageComponent(_) => self.message_popup.handle_event(ev)?,
             ComponentType::FullLogComponent(_) => self.log_popup.handle_event(ev)?,
             ComponentType::DiffComponent(diff_type) => match diff_type {
-                DiffComponentType::Diff => self.diff.handle_event(ev)?,
+                DiffComponentType::Diff | DiffComponentType::Stash(_) => {
+                    self.diff.handle_event(ev)?
+                }
                 DiffComponentType::Staged => self.diff_staged.handle_event(ev)?,
             },
             ComponentType::None => {}
@@ -171,12 +238,37 @@ impl App {
             GitEvent::FetchSuccess => self.message_popup.set_message("Fetch Successfull!"),
             GitEvent::RefreshCommitLog => self.logs.update()?,
             GitEvent::RefreshBranchList => self.branches.update()?,
+            GitEvent::CommitsLoaded(generation, commits, finished) => {
+                self.logs.add_commits(generation, commits, finished)
+            }
+            GitEvent::SearchResults(generation, commits, finished) => {
+                self.logs.add_search_results(generation, commits, finished)
+            }
+            GitEvent::SignaturesVerified(signatures) => {
+                self.log_popup.set_signatures(&signatures);
+                self.logs.set_signatures(signatures);
+            }
+            GitEvent::HookOutput(hook, line) => {
+                self.hook_popup.push_line(hook, line);
+                if !matches!(self.focused_component, ComponentType::HookComponent) {
+                    self.hook_popup.set_origin(self.focused_component.clone());
+                    self.focus(ComponentType::HookComponent);
+                }
+            }
+            GitEvent::MessagePrepared(amend, message, warning) => {
+                self.commit_popup.set_message(amend, &message, warning)
+            }
         }
         Ok(())
     }
 
     pub fn display_error(&mut self, error: anyhow::Error) {
-        self.error_popup.set_message(error.to_string());
+        // Keep what is already shown, like a commit a rebase dropped before
+        // it stopped
+        match self.focused_component {
+            ComponentType::ErrorComponent => self.error_popup.push_message(error.to_string()),
+            _ => self.error_popup.set_message(error.to_string()),
+        }
         self.focus(ComponentType::ErrorComponent);
     }
 
@@ -190,27 +282,76 @@ impl App {
         match component.clone() {
             ComponentType::LogComponent => self.logs.focus(focus),
             ComponentType::ErrorComponent => self.error_popup.focus(focus),
+            ComponentType::HookComponent => self.hook_popup.focus(focus),
+            ComponentType::IdentityComponent => self.identity_popup.focus(focus),
             ComponentType::BranchComponent => self.branches.focus(focus),
             ComponentType::FilesComponent => self.files.focus(focus),
-            ComponentType::CommitComponent => self.commit_popup.focus(focus),
+            ComponentType::CommitComponent => {
+                if focus {
+                    self.commit_popup.set_amend(false);
+                }
+                self.commit_popup.focus(focus);
+            }
+            ComponentType::AmendComponent => {
+                if focus {
+                    self.commit_popup.set_amend(true);
+                }
+                self.commit_popup.focus(focus);
+            }
+            ComponentType::ConflictComponent => self.conflict_popup.focus(focus),
             ComponentType::BranchPopupComponent => self.branch_popup.focus(focus),
             ComponentType::RemotePopupComponent => self.remote_popup.focus(focus),
+            ComponentType::StashComponent => self.stash.focus(focus),
             ComponentType::DiffComponent(diff_type) => match diff_type {
                 DiffComponentType::Diff => self.diff.focus(focus),
                 DiffComponentType::Staged => self.diff_staged.focus(focus),
+                DiffComponentType::Stash(id) => {
+                    // The stash preview only lasts while the diff pane is focused
+                    self.diff.set_diff_type(match focus {
+                        true => DiffComponentType::Stash(id),
+                        false => DiffComponentType::Diff,
+                    });
+                    self.diff.focus(focus);
+                }
             },
+            ComponentType::BlameComponent(target) => {
+                if focus {
+                    let origin = self.focused_component.clone();
+                    self.blame_popup.open(target, origin);
+                }
+                self.blame_popup.focus(focus);
+            }
             ComponentType::CherryPickPopup(logs) => {
                 self.cherry_pick_popup.set_logs(logs);
                 self.cherry_pick_popup.focus(focus);
             }
+            ComponentType::ConfirmComponent(action) => {
+                self.confirm_popup.set_action(action);
+                self.confirm_popup.focus(focus);
+            }
             ComponentType::MessageComponent(message) => {
                 self.message_popup.set_message(&message);
                 self.message_popup.focus(focus);
             }
             ComponentType::FullLogComponent(commit) => {
+                if focus {
+                    // Commits shown from a blame go back to it
+                    let origin = match self.blame_popup.target() {
+                        Some(target) => ComponentType::BlameComponent(target.clone()),
+                        None => ComponentType::LogComponent,
+                    };
+                    self.log_popup.set_origin(origin);
+                }
                 self.log_popup.set_commit(commit);
                 self.log_popup.focus(focus);
             }
+            ComponentType::PathLogComponent(path) => {
+                // Focused as the log pane itself once it's filtered
+                if focus {
+                    self.logs.set_path_filter(Some(PathFilter::new(path)));
+                }
+                return self._focus(ComponentType::LogComponent, focus);
+            }
             ComponentType::None => {}
         }
 
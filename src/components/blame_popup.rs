@@ -0,0 +1,262 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use crossbeam::channel::Sender;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph};
+use tui::Frame;
+
+use crate::app::ProgramEvent;
+use crate::components::{centered_rect, Component, ComponentType};
+use crate::git::blame::{blame_file, blame_parent, BlameLine, BlameTarget};
+use crate::git::log::find_commit;
+
+pub struct BlamePopup {
+    /// Shown in the popup, so it stays open when something goes wrong.
+    error: Option<String>,
+    event_sender: Sender<ProgramEvent>,
+    /// The blames left by going to a parent, and the line selected in each.
+    history: Vec<(BlameTarget, usize)>,
+    lines: Vec<BlameLine>,
+    /// Where to go back to when the popup is closed.
+    origin: ComponentType,
+    repo_path: PathBuf,
+    state: ListState,
+    target: Option<BlameTarget>,
+    visible: bool,
+}
+
+impl BlamePopup {
+    pub fn new(repo_path: PathBuf, event_sender: Sender<ProgramEvent>) -> Self {
+        Self {
+            error: None,
+            event_sender,
+            history: Vec::new(),
+            lines: Vec::new(),
+            origin: ComponentType::FilesComponent,
+            repo_path,
+            state: ListState::default(),
+            target: None,
+            visible: false,
+        }
+    }
+
+    pub fn draw<B: Backend>(&mut self, f: &mut Frame<B>, rect: Rect) -> Result<()> {
+        let area = centered_rect(
+            rect.width.saturating_sub(8).min(160),
+            rect.height.saturating_sub(4),
+            rect,
+        );
+
+        let title = match &self.target {
+            Some(BlameTarget {
+                path,
+                commit: Some(commit),
+            }) => format!(" Blame {} at {} ", path, &commit[0..8]),
+            Some(target) => format!(" Blame {} ", target.path),
+            None => String::new(),
+        };
+        let border = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+
+        let error_height = match self.error {
+            Some(_) => 1,
+            None => 0,
+        };
+        let container = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Min(1), Constraint::Length(error_height)].as_ref())
+            .split(area);
+
+        let number_width = self.lines.len().to_string().len();
+        let items = self
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                let id = match &line.commit {
+                    Some(commit) => commit[0..8].to_string(),
+                    None => "--------".to_string(),
+                };
+                let time = String::from(*line.time.time_since_commit());
+
+                ListItem::new(Spans::from(vec![
+                    Span::styled(id, Style::default().fg(Color::Green)),
+                    Span::styled(
+                        format!(" {:<16.16}", line.author),
+                        Style::default().fg(Color::Blue),
+                    ),
+                    Span::styled(format!(" {:>4}", time), Style::default().fg(Color::Yellow)),
+                    Span::styled(
+                        format!(" {:>width$} ", index + 1, width = number_width),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::raw(line.content.replace('\t', "    ")),
+                ]))
+            })
+            .collect::<Vec<_>>();
+
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
+
+        f.render_widget(Clear, area);
+        f.render_widget(border, area);
+        f.render_stateful_widget(list, container[0], &mut self.state);
+
+        if let Some(error) = &self.error {
+            let error = Paragraph::new(error.as_str()).style(Style::default().fg(Color::Red));
+            f.render_widget(error, container[1]);
+        }
+        Ok(())
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    /// The file blamed, while the popup is open.
+    pub fn target(&self) -> Option<&BlameTarget> {
+        self.target.as_ref()
+    }
+
+    /// Blames a file, going back to `origin` when closed. Opening the file
+    /// already blamed keeps the line selected, as after showing a commit.
+    pub fn open(&mut self, target: BlameTarget, origin: ComponentType) {
+        if self.target.as_ref() == Some(&target) {
+            return;
+        }
+
+        match blame_file(&self.repo_path, &target) {
+            Ok(lines) => {
+                self.origin = origin;
+                self.history.clear();
+                self.set_blame(target, lines, 0);
+            }
+            Err(err) => {
+                self.target = None;
+                self.event_sender
+                    .send(ProgramEvent::Error(err))
+                    .expect("Send failed.");
+            }
+        }
+    }
+
+    fn set_blame(&mut self, target: BlameTarget, lines: Vec<BlameLine>, selected: usize) {
+        self.error = None;
+        self.target = Some(target);
+        self.lines = lines;
+        self.state.select(match self.lines.is_empty() {
+            true => None,
+            false => Some(selected.min(self.lines.len() - 1)),
+        });
+    }
+
+    fn selected_line(&self) -> Option<&BlameLine> {
+        self.state
+            .selected()
+            .and_then(|index| self.lines.get(index))
+    }
+
+    fn move_line(&mut self, down: bool, amount: usize) {
+        if let Some(selected) = self.state.selected() {
+            let selected = match down {
+                true => (selected + amount).min(self.lines.len() - 1),
+                false => selected.saturating_sub(amount),
+            };
+            self.state.select(Some(selected));
+        }
+    }
+
+    fn show_commit(&mut self) {
+        let id = match self.selected_line().map(|line| line.commit.clone()) {
+            Some(Some(id)) => id,
+            Some(None) => {
+                self.error = Some(" The line isn't committed yet".to_string());
+                return;
+            }
+            None => return,
+        };
+
+        match find_commit(&self.repo_path, &id) {
+            Ok(commit) => self
+                .event_sender
+                .send(ProgramEvent::Focus(ComponentType::FullLogComponent(commit)))
+                .expect("Send failed."),
+            Err(err) => self.error = Some(format!(" {}", err)),
+        }
+    }
+
+    /// Blames the file as it was before the selected line's commit.
+    fn blame_parent(&mut self) {
+        let (line, current) = match (self.selected_line(), &self.target) {
+            (Some(line), Some(current)) => (line.clone(), current.clone()),
+            _ => return,
+        };
+        let selected = self.state.selected().unwrap_or(0);
+
+        let result = blame_parent(&self.repo_path, &line)
+            .and_then(|target| Ok((blame_file(&self.repo_path, &target)?, target)));
+        match result {
+            Ok((lines, target)) => {
+                self.history.push((current, selected));
+                self.set_blame(target, lines, selected);
+            }
+            Err(err) => self.error = Some(format!(" {}", err)),
+        }
+    }
+
+    fn go_back(&mut self) {
+        if let Some((target, selected)) = self.history.pop() {
+            match blame_file(&self.repo_path, &target) {
+                Ok(lines) => self.set_blame(target, lines, selected),
+                Err(err) => self.error = Some(format!(" {}", err)),
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.event_sender
+            .send(ProgramEvent::Focus(self.origin.clone()))
+            .expect("Focus event send failed.");
+        self.visible = false;
+        self.target = None;
+        self.lines.clear();
+        self.history.clear();
+    }
+}
+
+impl Component for BlamePopup {
+    fn handle_event(&mut self, ev: KeyEvent) -> Result<()> {
+        match ev.code {
+            KeyCode::Char('j') => self.move_line(true, 1),
+            KeyCode::Char('k') => self.move_line(false, 1),
+            KeyCode::Char('d') if ev.modifiers == KeyModifiers::CONTROL => self.move_line(true, 10),
+            KeyCode::Char('u') if ev.modifiers == KeyModifiers::CONTROL => {
+                self.move_line(false, 10)
+            }
+            KeyCode::Char('p') => self.blame_parent(),
+            KeyCode::Backspace => self.go_back(),
+            KeyCode::Enter => self.show_commit(),
+            KeyCode::Esc => self.reset(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn focus(&mut self, focus: bool) {
+        // Nothing is shown when the file couldn't be blamed
+        self.visible = focus && self.target.is_some();
+    }
+
+    fn update(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
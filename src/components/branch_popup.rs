@@ -106,17 +106,13 @@ impl Component for BranchPopup {
                     }
                 }
             }
-            KeyCode::Char(c) => {
-                if self.input.len() < 75 {
-                    self.cursor_position.0 += 1;
-                    self.input.push(c);
-                }
+            KeyCode::Char(c) if self.input.len() < 75 => {
+                self.cursor_position.0 += 1;
+                self.input.push(c);
             }
-            KeyCode::Backspace => {
-                if !self.input.is_empty() {
-                    self.cursor_position.0 -= 1;
-                    self.input.pop();
-                }
+            KeyCode::Backspace if !self.input.is_empty() => {
+                self.cursor_position.0 -= 1;
+                self.input.pop();
             }
             KeyCode::Enter => {
                 let input = self.input.clone();
@@ -1,11 +1,11 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::thread;
 use std::time::Duration;
 
 use anyhow::Result;
 use crossbeam::channel::{unbounded, Sender};
 use crossterm::event::{KeyCode, KeyEvent};
-use git2::{BranchType, MergeOptions};
+use git2::BranchType;
 use tui::backend::Backend;
 use tui::layout::{Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
@@ -15,11 +15,12 @@ use tui::Frame;
 
 use crate::app::{GitEvent, ProgramEvent};
 use crate::component_style::ComponentTheme;
+use crate::components::confirm_popup::ConfirmAction;
 use crate::components::{Component, ScrollableComponent};
 use crate::git::branch::{
-    checkout_local_branch, checkout_remote_branch, delete_branch, get_branches, Branch,
+    checkout_branch, checkout_conflicts, delete_branch, get_branches, Branch,
 };
-use crate::git::commit::merge_commit;
+use crate::git::commit::merge_into_head;
 use crate::git::fetch::{fetch, pull_head, pull_selected};
 use crate::git::log::collect_commits;
 use crate::git::repo;
@@ -131,10 +132,25 @@ impl BranchComponent {
 
     fn checkout_branch(&self) -> Result<()> {
         if let Some(branch) = self.branches.get(self.position) {
-            if branch.branch_type == git2::BranchType::Local {
-                checkout_local_branch(&self.repo_path, &branch.name)?;
-            } else {
-                checkout_remote_branch(&self.repo_path, &branch.name)?;
+            let conflicts = checkout_conflicts(&self.repo_path, &branch.name)?;
+
+            if !conflicts.is_empty() {
+                self.event_sender
+                    .send(ProgramEvent::Focus(ComponentType::ConfirmComponent(
+                        ConfirmAction::AutostashCheckout(
+                            branch.name.clone(),
+                            branch.branch_type,
+                            conflicts,
+                        ),
+                    )))
+                    .expect("Send failed.");
+                return Ok(());
+            }
+
+            if let Err(err) = checkout_branch(&self.repo_path, &branch.name, branch.branch_type) {
+                self.event_sender
+                    .send(ProgramEvent::Error(err))
+                    .expect("Send failed.");
             }
         }
 
@@ -209,57 +225,56 @@ impl BranchComponent {
     }
 
     fn merge(&self) -> Result<()> {
-        let repo = repo(&self.repo_path)?;
         if let Some(branch) = self.branches.get(self.position) {
             let refname = format!("refs/heads/{}", branch.name);
-            match repo.find_reference(&refname) {
-                Ok(reference) => {
-                    let mut opts = MergeOptions::new();
-                    let annotated_commit = repo.reference_to_annotated_commit(&reference)?;
-
-                    repo.merge(
-                        &[&annotated_commit],
-                        Some(&mut opts),
-                        Some(git2::build::CheckoutBuilder::default().force()),
-                    )?;
-
-                    if let Err(err) = merge_commit(&self.repo_path, annotated_commit) {
-                        self.event_sender
-                            .send(ProgramEvent::Error(err))
-                            .expect("Send failed");
-                    }
-                }
-                Err(err) => {
-                    self.event_sender
-                        .send(ProgramEvent::Error(anyhow::Error::from(err)))
-                        .expect("Send failed");
-                }
-            }
+            let reflog = format!("merge {}", branch.name);
+            self.in_background(move |repo_path, event_sender| {
+                let repo = repo(repo_path)?;
+                let reference = repo.find_reference(&refname)?;
+                let annotated_commit = repo.reference_to_annotated_commit(&reference)?;
+                merge_into_head(repo_path, &repo, annotated_commit, &reflog, event_sender)
+            });
         }
 
-        repo.cleanup_state()?;
-
         Ok(())
     }
 
     fn pull_selected_branch(&self) {
-        let (progress_sender, _progress_receiver) = unbounded();
         if let Some(branch) = self.branches.get(self.position) {
-            if let Err(err) = pull_selected(&self.repo_path, &branch.name, progress_sender) {
-                self.event_sender
-                    .send(ProgramEvent::Error(err))
-                    .expect("Push failure event send failed.");
-            }
+            let name = branch.name.clone();
+            self.in_background(move |repo_path, event_sender| {
+                let (progress_sender, _progress_receiver) = unbounded();
+                pull_selected(repo_path, &name, progress_sender, event_sender)
+            });
         }
     }
 
     fn pull_head(&self) {
-        let (progress_sender, _progress_receiver) = unbounded();
-        if let Err(err) = pull_head(&self.repo_path, progress_sender) {
-            self.event_sender
-                .send(ProgramEvent::Error(err))
-                .expect("Push failure event send failed.");
-        }
+        self.in_background(|repo_path, event_sender| {
+            let (progress_sender, _progress_receiver) = unbounded();
+            pull_head(repo_path, progress_sender, event_sender)
+        });
+    }
+
+    /// Merges and pulls run in the background, as the commit hooks may take
+    /// a while.
+    fn in_background<F>(&self, task: F)
+    where
+        F: FnOnce(&Path, &Sender<ProgramEvent>) -> Result<()> + Send + 'static,
+    {
+        let repo_path = self.repo_path.clone();
+        let event_sender = self.event_sender.clone();
+
+        thread::spawn(move || {
+            if let Err(err) = task(&repo_path, &event_sender) {
+                event_sender
+                    .send(ProgramEvent::Error(err))
+                    .expect("Send failed.");
+            }
+            event_sender
+                .send(ProgramEvent::Git(GitEvent::RefreshCommitLog))
+                .expect("Send failed.");
+        });
     }
 }
 
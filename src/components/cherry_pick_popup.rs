@@ -1,4 +1,5 @@
 use std::path::PathBuf;
+use std::thread;
 
 use anyhow::Result;
 use crossbeam::channel::Sender;
@@ -10,6 +11,7 @@ use tui::text::{Span, Spans};
 use tui::widgets::{Block, BorderType, Borders, Clear, List as TuiList, ListItem, ListState};
 use tui::Frame;
 
+use crate::app::GitEvent;
 use crate::components::{centered_rect, Component, ComponentType, ScrollableComponent};
 use crate::git::commit::cherry_pick;
 use crate::git::log::Commit;
@@ -95,13 +97,22 @@ impl CherryPickPopup {
         self.visible = false;
     }
 
+    /// Picks in the background, as the commit hooks may take a while.
     fn cherry_pick(&mut self) -> Result<()> {
-        if let Err(err) = cherry_pick(&self.repo_path, &self.selected_commits) {
-            self.event_sender
-                .send(ProgramEvent::Error(err))
+        let commits = std::mem::take(&mut self.selected_commits);
+        let repo_path = self.repo_path.clone();
+        let event_sender = self.event_sender.clone();
+
+        thread::spawn(move || {
+            if let Err(err) = cherry_pick(&repo_path, &commits, &event_sender) {
+                event_sender
+                    .send(ProgramEvent::Error(err))
+                    .expect("Send Failed");
+            }
+            event_sender
+                .send(ProgramEvent::Git(GitEvent::RefreshCommitLog))
                 .expect("Send Failed");
-        }
-        self.selected_commits.clear();
+        });
         Ok(())
     }
 
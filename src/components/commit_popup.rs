@@ -1,129 +1,523 @@
 use std::path::PathBuf;
+use std::thread;
 
 use anyhow::Result;
 use crossbeam::channel::Sender;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use tui::backend::Backend;
-use tui::layout::{Alignment, Rect};
-use tui::style::Style;
-use tui::widgets::{Block, Borders, Clear, Paragraph};
+use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph};
 use tui::Frame;
 
 use crate::app::{GitEvent, ProgramEvent};
 use crate::components::{centered_rect, Component, ComponentType};
-use crate::git::commit::commit;
+use crate::git::commit::{amend, commit, head_pushed_to, prepare_message};
+use crate::git::lint::{
+    cycle_type, has_errors, lint_config, lint_message, LintConfig, LintIssue, Severity, BODY_WIDTH,
+};
+use crate::git::trailer::{
+    add_trailer, remove_trailer, repo_authors, sign_off_by_default, sign_off_identity,
+    trailer_range, CO_AUTHORED_BY, SIGNED_OFF_BY,
+};
+use crate::text_area::TextArea;
 
 pub struct CommitPopup {
-    cursor_position: (u16, u16),
-    cursor_visible: bool,
+    amend: bool,
+    amend_warning: Option<String>,
+    /// Authors to pick co-authors from, loaded when the picker first opens.
+    authors: Vec<String>,
+    author_filter: String,
+    author_state: ListState,
     event_sender: Sender<ProgramEvent>,
-    input: String,
+    lint_config: LintConfig,
+    picking_author: bool,
+    /// Whether the message being edited was prepared for amending, `None`
+    /// until it's prepared.
+    prepared: Option<bool>,
     repo_path: PathBuf,
+    scroll: usize,
+    text_area: TextArea,
     visible: bool,
 }
 
 impl CommitPopup {
     pub fn new(repo_path: PathBuf, event_sender: Sender<ProgramEvent>) -> Self {
         Self {
-            cursor_position: (0, 0),
-            cursor_visible: false,
+            amend: false,
+            amend_warning: None,
+            authors: Vec::new(),
+            author_filter: String::new(),
+            author_state: ListState::default(),
             event_sender,
-            input: String::new(),
+            lint_config: LintConfig::default(),
+            picking_author: false,
+            prepared: None,
             repo_path,
+            scroll: 0,
+            text_area: TextArea::new(),
             visible: false,
         }
     }
 
     pub fn draw<B: Backend>(&mut self, f: &mut Frame<B>, rect: Rect) -> Result<()> {
-        let area = centered_rect(100, 3, rect);
+        let area = centered_rect(
+            rect.width.min(BODY_WIDTH as u16 + 4),
+            rect.height.min(20),
+            rect,
+        );
+        f.render_widget(Clear, area);
+
+        let issues = self.lint();
+        let container = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Min(4),
+                    Constraint::Length(match (self.picking_author, issues.len()) {
+                        (true, _) => 8,
+                        (false, 0) => 0,
+                        (false, len) => len.min(4) as u16 + 2,
+                    }),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        match self.picking_author {
+            true => self.draw_authors(f, container[1]),
+            false => self.draw_issues(f, container[1], &issues),
+        }
+
+        let area = container[0];
+        let width = area.width.saturating_sub(2) as usize;
+        // One row goes to the ruler
+        let height = area.height.saturating_sub(3) as usize;
 
-        if !self.cursor_visible {
-            f.set_cursor(area.x + 1, area.y + 1);
-            self.cursor_position = (area.x + 1, area.y + 1);
-            self.cursor_visible = true;
-        } else {
-            f.set_cursor(self.cursor_position.0, self.cursor_position.1);
+        let (row, col) = self.text_area.cursor();
+        if row < self.scroll {
+            self.scroll = row;
+        } else if height > 0 && row >= self.scroll + height {
+            self.scroll = row + 1 - height;
         }
+        let offset = (col + 1).saturating_sub(width);
+
+        let title = match (self.amend, &self.amend_warning) {
+            (true, Some(upstream)) => Span::styled(
+                format!(
+                    " Amend - HEAD is already pushed to {}, amending rewrites it ",
+                    upstream
+                ),
+                Style::default().fg(Color::Yellow),
+            ),
+            (true, None) => Span::raw(" Amend "),
+            (false, _) => Span::raw(" Commit "),
+        };
+
+        let summary_width = self.lint_config.summary_length;
+        let trailers = trailer_range(self.text_area.lines()).unwrap_or_default();
+        let mut lines = vec![ruler(offset, width, summary_width)];
+        lines.extend(
+            self.text_area
+                .lines()
+                .iter()
+                .enumerate()
+                .skip(self.scroll)
+                .take(height)
+                .map(|(index, line)| match index {
+                    0 => styled_line(line, Some(summary_width), offset),
+                    index if trailers.contains(&index) => trailer_line(line, offset),
+                    _ => styled_line(line, None, offset),
+                }),
+        );
+
+        let input = Paragraph::new(lines).style(Style::default()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_alignment(Alignment::Left),
+        );
+
+        f.render_widget(input, area);
+        f.set_cursor(
+            area.x + 1 + (col - offset) as u16,
+            area.y + 2 + (row - self.scroll) as u16,
+        );
+        Ok(())
+    }
+
+    fn draw_issues<B: Backend>(&self, f: &mut Frame<B>, area: Rect, issues: &[LintIssue]) {
+        let lines = issues
+            .iter()
+            .map(|issue| {
+                let color = match issue.severity {
+                    Severity::Warning => Color::Yellow,
+                    Severity::Error => Color::Red,
+                };
+                Spans::from(Span::styled(
+                    issue.message.clone(),
+                    Style::default().fg(color),
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        let title = match has_errors(issues) {
+            true => " Fix these to commit ",
+            false => " Warnings ",
+        };
+        let issues = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_alignment(Alignment::Left),
+        );
+        f.render_widget(issues, area);
+    }
+
+    fn draw_authors<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+        let items = self
+            .filtered_authors()
+            .into_iter()
+            .map(|author| ListItem::new(author.clone()))
+            .collect::<Vec<_>>();
 
-        let input = Paragraph::new(self.input.as_ref())
-            .style(Style::default())
+        let title = match self.author_filter.is_empty() {
+            true => " Co-author (type to filter) ".to_string(),
+            false => format!(" Co-author: {} ", self.author_filter),
+        };
+        let list = List::new(items)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title(" Commit ")
+                    .title(title)
                     .title_alignment(Alignment::Left),
-            );
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
 
-        f.render_widget(Clear, area);
-        f.render_widget(input, area);
-        Ok(())
+        f.render_stateful_widget(list, area, &mut self.author_state);
     }
 
     pub fn visible(&self) -> bool {
         self.visible
     }
 
+    /// Switches between committing and amending HEAD. The message of the
+    /// commit being amended, or the commit template, is prepared in the
+    /// background as it goes through the `prepare-commit-msg` hook, and
+    /// comes back through `set_message`.
+    pub fn set_amend(&mut self, amend: bool) {
+        // Coming back from the hook output keeps what's been written
+        if self.prepared == Some(amend) && !self.text_area.text().is_empty() {
+            return;
+        }
+        self.amend = amend;
+        self.amend_warning = None;
+        self.prepared = Some(amend);
+        self.text_area.clear();
+
+        let repo_path = self.repo_path.clone();
+        let event_sender = self.event_sender.clone();
+
+        thread::spawn(move || {
+            let message = prepare_message(&repo_path, amend, &event_sender).and_then(|message| {
+                let warning = match amend {
+                    true => head_pushed_to(&repo_path)?,
+                    false => None,
+                };
+                Ok((message, warning))
+            });
+
+            let event = match message {
+                Ok((message, warning)) => {
+                    ProgramEvent::Git(GitEvent::MessagePrepared(amend, message, warning))
+                }
+                Err(err) => ProgramEvent::Error(err),
+            };
+            event_sender.send(event).expect("Send failed.");
+        });
+    }
+
+    /// Fills in the prepared message, unless the popup has moved on or
+    /// something was typed while the hook ran.
+    pub fn set_message(&mut self, amend: bool, message: &str, warning: Option<String>) {
+        if self.prepared != Some(amend) || !self.text_area.text().is_empty() {
+            return;
+        }
+
+        self.text_area.set_text(message.trim_end());
+        self.amend_warning = warning;
+        if !amend && sign_off_by_default(&self.repo_path).unwrap_or(false) {
+            self.toggle_sign_off(true);
+        }
+    }
+
     fn reset(&mut self) {
         self.event_sender
             .send(ProgramEvent::Focus(ComponentType::FilesComponent))
             .expect("Focus event send failed.");
-        self.cursor_visible = false;
         self.visible = false;
-        self.input.clear();
+        self.prepared = None;
+        self.scroll = 0;
+        self.text_area.clear();
+        self.authors.clear();
+        self.close_author_picker();
     }
 
-    fn commit(&mut self) -> Result<()> {
-        if self.input.is_empty() {
-            return Ok(());
+    /// The message as git would store it, with comment lines, leading blank
+    /// lines and trailing whitespace removed.
+    fn message(&self) -> String {
+        let text = self.text_area.text();
+        let lines = text
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| line.trim_end())
+            .skip_while(|line| line.is_empty())
+            .collect::<Vec<_>>();
+
+        lines.join("\n").trim_end().to_string()
+    }
+
+    fn lint(&self) -> Vec<LintIssue> {
+        let message = self.message();
+        if message.is_empty() {
+            return Vec::new();
         }
+        lint_message(&message, &self.lint_config)
+    }
 
-        commit(&self.repo_path, &self.input, None)?;
-        Ok(())
+    fn cycle_type(&mut self) {
+        if let Some(summary) = self.text_area.lines().first() {
+            let summary = cycle_type(summary, &self.lint_config.types);
+            self.text_area.set_line(0, summary);
+        }
+    }
+
+    /// Edits the message's lines, keeping the cursor where it was.
+    fn edit_lines<F: FnOnce(&mut Vec<String>)>(&mut self, edit: F) {
+        let (row, col) = self.text_area.cursor();
+        let mut lines = self.text_area.lines().to_vec();
+        edit(&mut lines);
+        self.text_area.set_text(&lines.join("\n"));
+        self.text_area.set_cursor(row, col);
+    }
+
+    /// Adds the committer's `Signed-off-by`, or removes it if it's there
+    /// and `only_add` isn't set.
+    fn toggle_sign_off(&mut self, only_add: bool) {
+        let identity = match sign_off_identity(&self.repo_path) {
+            Ok(identity) => identity,
+            Err(err) => {
+                self.event_sender
+                    .send(ProgramEvent::Error(err))
+                    .expect("Send failed.");
+                return;
+            }
+        };
+
+        self.edit_lines(|lines| {
+            if only_add || !remove_trailer(lines, SIGNED_OFF_BY, &identity) {
+                add_trailer(lines, SIGNED_OFF_BY, &identity);
+            }
+        });
+    }
+
+    fn open_author_picker(&mut self) {
+        if self.authors.is_empty() {
+            match repo_authors(&self.repo_path) {
+                Ok(authors) => self.authors = authors,
+                Err(err) => {
+                    self.event_sender
+                        .send(ProgramEvent::Error(err))
+                        .expect("Send failed.");
+                    return;
+                }
+            }
+        }
+
+        self.picking_author = true;
+        self.author_state.select(Some(0));
+    }
+
+    fn close_author_picker(&mut self) {
+        self.picking_author = false;
+        self.author_filter.clear();
+    }
+
+    fn filtered_authors(&self) -> Vec<&String> {
+        let filter = self.author_filter.to_lowercase();
+        self.authors
+            .iter()
+            .filter(|author| author.to_lowercase().contains(&filter))
+            .collect()
+    }
+
+    fn pick_author(&mut self) {
+        let author = self
+            .author_state
+            .selected()
+            .and_then(|index| self.filtered_authors().get(index).map(|a| a.to_string()));
+        self.close_author_picker();
+
+        if let Some(author) = author {
+            self.edit_lines(|lines| add_trailer(lines, CO_AUTHORED_BY, &author));
+        }
+    }
+
+    fn handle_author_picker(&mut self, ev: KeyEvent) {
+        let selected = self.author_state.selected().unwrap_or(0);
+
+        match ev.code {
+            KeyCode::Char(c) if !ev.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.author_filter.push(c);
+                self.author_state.select(Some(0));
+            }
+            KeyCode::Backspace => {
+                self.author_filter.pop();
+                self.author_state.select(Some(0));
+            }
+            KeyCode::Down if selected + 1 < self.filtered_authors().len() => {
+                self.author_state.select(Some(selected + 1))
+            }
+            KeyCode::Up if selected > 0 => self.author_state.select(Some(selected - 1)),
+            KeyCode::Enter => self.pick_author(),
+            KeyCode::Esc => self.close_author_picker(),
+            _ => {}
+        }
+    }
+
+    /// Commits in the background so hook output shows up as it's written.
+    fn commit(&mut self) {
+        let message = self.message();
+        self.reset();
+        if message.is_empty() {
+            return;
+        }
+
+        let amend_head = self.amend;
+        let repo_path = self.repo_path.clone();
+        let event_sender = self.event_sender.clone();
+
+        thread::spawn(move || {
+            let result = match amend_head {
+                true => amend(&repo_path, &message, &event_sender),
+                false => commit(&repo_path, &message, None, None, &event_sender),
+            };
+
+            if let Err(err) = result {
+                event_sender
+                    .send(ProgramEvent::Error(err))
+                    .expect("Send failed");
+            }
+            event_sender
+                .send(ProgramEvent::Git(GitEvent::RefreshCommitLog))
+                .expect("Send failed");
+        });
+    }
+}
+
+/// Marks the summary and body width limits above the text.
+fn ruler(offset: usize, width: usize, summary_width: usize) -> Spans<'static> {
+    let ruler = (offset..offset + width)
+        .map(|col| match col + 1 {
+            col if col == summary_width || col == BODY_WIDTH => '|',
+            _ => ' ',
+        })
+        .collect::<String>();
+
+    Spans::from(Span::styled(ruler, Style::default().fg(Color::DarkGray)))
+}
+
+/// Colours the key of a trailer line.
+fn trailer_line(line: &str, offset: usize) -> Spans<'_> {
+    let line = line.chars().skip(offset).collect::<String>();
+    match line.split_once(':') {
+        Some((key, value)) => Spans::from(vec![
+            Span::styled(key.to_string(), Style::default().fg(Color::Cyan)),
+            Span::raw(format!(":{}", value)),
+        ]),
+        None => Spans::from(line),
     }
 }
 
+/// Highlights the part of a line past the summary width, if it's the
+/// summary, and past the body width.
+fn styled_line(line: &str, summary_width: Option<usize>, offset: usize) -> Spans<'_> {
+    let chars = line.chars().skip(offset).collect::<Vec<_>>();
+    let split = |from: usize, to: usize| {
+        let from = from.saturating_sub(offset).min(chars.len());
+        let to = to.saturating_sub(offset).min(chars.len());
+        chars[from..to].iter().collect::<String>()
+    };
+
+    let (base, within, over) = match summary_width {
+        Some(width) => (
+            Style::default().add_modifier(Modifier::BOLD),
+            width.min(BODY_WIDTH),
+            Style::default().fg(Color::Yellow),
+        ),
+        None => (Style::default(), BODY_WIDTH, Style::default()),
+    };
+
+    Spans::from(vec![
+        Span::styled(split(0, within), base),
+        Span::styled(split(within, BODY_WIDTH), base.patch(over)),
+        Span::styled(
+            split(BODY_WIDTH, usize::MAX),
+            base.patch(Style::default().fg(Color::Red)),
+        ),
+    ])
+}
+
 impl Component for CommitPopup {
     fn handle_event(&mut self, ev: KeyEvent) -> Result<()> {
         if !self.visible {
             return Ok(());
         }
 
+        if self.picking_author {
+            self.handle_author_picker(ev);
+            return Ok(());
+        }
+
         match ev.code {
-            KeyCode::Char('w') if ev.modifiers == KeyModifiers::CONTROL => {
-                let input_ref = self.input.as_str().trim();
-                let last_space_index = input_ref.rfind(' ');
-                match last_space_index {
-                    Some(index) => {
-                        let cursor_index =
-                            self.cursor_position.0 - (self.input.len() - index) as u16;
-                        self.input = self.input[0..index].to_string();
-                        self.cursor_position.0 = cursor_index;
-                    }
-                    None => {
-                        self.cursor_position.0 -= self.input.len() as u16;
-                        self.input.clear();
-                    }
+            KeyCode::Char('s') if ev.modifiers == KeyModifiers::CONTROL => {
+                // The issues are already on screen
+                if has_errors(&self.lint()) {
+                    return Ok(());
                 }
+
+                self.commit();
             }
-            KeyCode::Char(c) => {
-                if self.input.len() < 95 {
-                    self.cursor_position.0 += 1;
-                    self.input.push(c);
-                }
+            KeyCode::Char('w') if ev.modifiers == KeyModifiers::CONTROL => {
+                self.text_area.delete_word()
             }
-            KeyCode::Backspace => {
-                if !self.input.is_empty() {
-                    self.cursor_position.0 -= 1;
-                    self.input.pop();
-                }
+            KeyCode::Char('t') if ev.modifiers == KeyModifiers::CONTROL => self.cycle_type(),
+            KeyCode::Char('o') if ev.modifiers == KeyModifiers::CONTROL => {
+                self.open_author_picker()
             }
-            KeyCode::Enter => {
-                self.commit()?;
-                self.reset();
-                self.event_sender
-                    .send(ProgramEvent::Git(GitEvent::RefreshCommitLog))
-                    .expect("Send failed");
+            KeyCode::Char('g') if ev.modifiers == KeyModifiers::CONTROL => {
+                self.toggle_sign_off(false)
+            }
+            KeyCode::Char('a') if ev.modifiers == KeyModifiers::CONTROL => {
+                self.text_area.move_home()
+            }
+            KeyCode::Char('e') if ev.modifiers == KeyModifiers::CONTROL => {
+                self.text_area.move_end()
             }
+            KeyCode::Char(_) if ev.modifiers.contains(KeyModifiers::CONTROL) => {}
+            KeyCode::Char(c) => self.text_area.insert_char(c),
+            // Pasted text arrives as key presses, so newlines come in as Enter
+            KeyCode::Enter => self.text_area.insert_newline(),
+            KeyCode::Tab => (0..4).for_each(|_| self.text_area.insert_char(' ')),
+            KeyCode::Backspace => self.text_area.backspace(),
+            KeyCode::Delete => self.text_area.delete(),
+            KeyCode::Left => self.text_area.move_left(),
+            KeyCode::Right => self.text_area.move_right(),
+            KeyCode::Up => self.text_area.move_up(),
+            KeyCode::Down => self.text_area.move_down(),
+            KeyCode::Home => self.text_area.move_home(),
+            KeyCode::End => self.text_area.move_end(),
             KeyCode::Esc => {
                 self.reset();
             }
@@ -135,6 +529,16 @@ impl Component for CommitPopup {
 
     fn focus(&mut self, focus: bool) {
         self.visible = focus;
+
+        if focus {
+            match lint_config(&self.repo_path) {
+                Ok(config) => self.lint_config = config,
+                Err(err) => self
+                    .event_sender
+                    .send(ProgramEvent::Error(err))
+                    .expect("Send failed."),
+            }
+        }
     }
 
     fn update(&mut self) -> Result<()> {
@@ -0,0 +1,217 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use crossbeam::channel::Sender;
+use crossterm::event::{KeyCode, KeyEvent};
+use git2::BranchType;
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Text};
+use tui::widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph};
+use tui::Frame;
+
+use crate::app::ProgramEvent;
+use crate::components::{centered_rect, Component, ComponentType};
+use crate::git::branch::checkout_with_autostash;
+use crate::git::diff::{DiffComponentType, HunkHeader};
+use crate::git::discard::{delete_untracked_file, discard_file, discard_hunk, discard_patch};
+use crate::git::stash::stash_drop;
+use crate::git::state::abort_operation;
+
+/// Destructive actions that have to be confirmed before they run.
+#[derive(Clone, Debug)]
+pub enum ConfirmAction {
+    AbortOperation(String),
+    AutostashCheckout(String, BranchType, Vec<String>),
+    DeleteUntrackedFile(String),
+    DiscardFile(String),
+    DiscardHunk(String, HunkHeader),
+    DiscardLines(Vec<String>),
+    DropStash(usize),
+    None,
+}
+
+impl ConfirmAction {
+    fn message(&self) -> String {
+        match self {
+            ConfirmAction::AbortOperation(state) => format!(
+                "Abort {}? The files it changed, and any conflicts resolved in them, will be reset. Other local changes are kept.",
+                state.to_lowercase()
+            ),
+            ConfirmAction::AutostashCheckout(branch, _, conflicts) => {
+                let files = match conflicts.len() {
+                    1 => conflicts[0].clone(),
+                    n => format!("{} files", n),
+                };
+                format!(
+                    "Local changes to {} would be overwritten. Stash them and checkout {}?",
+                    files, branch
+                )
+            }
+            ConfirmAction::DeleteUntrackedFile(path) => format!("Delete untracked file {}?", path),
+            ConfirmAction::DiscardFile(path) => format!("Discard all changes to {}?", path),
+            ConfirmAction::DiscardHunk(path, _) => format!("Discard hunk in {}?", path),
+            ConfirmAction::DiscardLines(_) => "Discard selected lines?".to_string(),
+            ConfirmAction::DropStash(index) => format!("Drop stash@{{{}}}?", index),
+            ConfirmAction::None => String::new(),
+        }
+    }
+
+    fn return_focus(&self) -> ComponentType {
+        match self {
+            ConfirmAction::AbortOperation(_) => ComponentType::FilesComponent,
+            ConfirmAction::AutostashCheckout(_, _, _) => ComponentType::BranchComponent,
+            ConfirmAction::DeleteUntrackedFile(_) | ConfirmAction::DiscardFile(_) => {
+                ComponentType::FilesComponent
+            }
+            ConfirmAction::DiscardHunk(_, _) | ConfirmAction::DiscardLines(_) => {
+                ComponentType::DiffComponent(DiffComponentType::Diff)
+            }
+            ConfirmAction::DropStash(_) => ComponentType::StashComponent,
+            ConfirmAction::None => ComponentType::FilesComponent,
+        }
+    }
+}
+
+pub struct ConfirmPopup {
+    action: ConfirmAction,
+    event_sender: Sender<ProgramEvent>,
+    repo_path: PathBuf,
+    state: ListState,
+    visible: bool,
+}
+
+impl ConfirmPopup {
+    pub fn new(repo_path: PathBuf, event_sender: Sender<ProgramEvent>) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(1));
+
+        Self {
+            action: ConfirmAction::None,
+            event_sender,
+            repo_path,
+            state,
+            visible: false,
+        }
+    }
+
+    pub fn draw<B: Backend>(&mut self, f: &mut Frame<B>, rect: Rect) -> Result<()> {
+        let area = centered_rect(50, 9, rect);
+
+        let border = Block::default()
+            .title(Span::styled(" Confirm ", Style::default().fg(Color::Red)))
+            .style(Style::default())
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+
+        let container = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Length(4), Constraint::Length(3)].as_ref())
+            .split(area);
+
+        let prompt = Paragraph::new(Text::raw(format!("\n{}", self.action.message())))
+            .alignment(tui::layout::Alignment::Center)
+            .style(Style::default().fg(Color::White))
+            .wrap(tui::widgets::Wrap { trim: true });
+
+        let options = vec![ListItem::new("Yes"), ListItem::new("No")];
+
+        let list = List::new(options)
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Blue)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ");
+
+        f.render_widget(Clear, area);
+        f.render_widget(border, area);
+        f.render_widget(prompt, container[0]);
+        f.render_stateful_widget(list, container[1], &mut self.state);
+
+        Ok(())
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_action(&mut self, action: ConfirmAction) {
+        self.action = action;
+    }
+
+    fn confirm(&mut self) {
+        let action = std::mem::replace(&mut self.action, ConfirmAction::None);
+        self.reset(&action);
+
+        let result = match &action {
+            ConfirmAction::AbortOperation(_) => abort_operation(&self.repo_path),
+            ConfirmAction::AutostashCheckout(branch, branch_type, _) => {
+                checkout_with_autostash(&self.repo_path, branch, *branch_type)
+            }
+            ConfirmAction::DeleteUntrackedFile(path) => {
+                delete_untracked_file(&self.repo_path, path)
+            }
+            ConfirmAction::DiscardFile(path) => discard_file(&self.repo_path, path),
+            ConfirmAction::DiscardHunk(path, hunk) => discard_hunk(&self.repo_path, path, *hunk),
+            ConfirmAction::DiscardLines(patches) => patches
+                .iter()
+                .try_for_each(|patch| discard_patch(&self.repo_path, patch)),
+            ConfirmAction::DropStash(index) => stash_drop(&self.repo_path, *index),
+            ConfirmAction::None => Ok(()),
+        };
+
+        if let Err(err) = result {
+            self.event_sender
+                .send(ProgramEvent::Error(err))
+                .expect("Send failed.");
+        }
+    }
+
+    fn cancel(&mut self) {
+        let action = std::mem::replace(&mut self.action, ConfirmAction::None);
+        self.reset(&action);
+    }
+
+    fn reset(&mut self, action: &ConfirmAction) {
+        self.event_sender
+            .send(ProgramEvent::Focus(action.return_focus()))
+            .expect("Focus event send failed.");
+        self.visible = false;
+        self.state.select(Some(1));
+    }
+}
+
+impl Component for ConfirmPopup {
+    fn update(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn handle_event(&mut self, ev: KeyEvent) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+
+        match ev.code {
+            KeyCode::Char('j') => self.state.select(Some(1)),
+            KeyCode::Char('k') => self.state.select(Some(0)),
+            KeyCode::Char('y') => self.confirm(),
+            KeyCode::Char('n') | KeyCode::Esc => self.cancel(),
+            KeyCode::Enter => {
+                if self.state.selected() == Some(0) {
+                    self.confirm();
+                } else {
+                    self.cancel();
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn focus(&mut self, focus: bool) {
+        self.visible = focus;
+    }
+}
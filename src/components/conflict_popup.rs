@@ -0,0 +1,318 @@
+use std::path::PathBuf;
+use std::thread;
+
+use anyhow::Result;
+use crossbeam::channel::Sender;
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans, Text};
+use tui::widgets::{
+    Block, BorderType, Borders, Clear, List as TuiList, ListItem, ListState, Paragraph,
+};
+use tui::Frame;
+
+use crate::app::{GitEvent, ProgramEvent};
+use crate::components::{centered_rect, Component, ComponentType, ScrollableComponent};
+use crate::git::conflict::{
+    get_conflict_hunks, mark_resolved, resolve_conflict_file, resolve_conflict_hunk, ConflictHunk,
+    Resolution,
+};
+use crate::git::state::continue_operation;
+use crate::git::status::get_conflicted_paths;
+
+pub struct ConflictPopup {
+    event_sender: Sender<ProgramEvent>,
+    files: Vec<String>,
+    hunk_index: usize,
+    hunks: Vec<ConflictHunk>,
+    position: usize,
+    repo_path: PathBuf,
+    state: ListState,
+    visible: bool,
+}
+
+impl ConflictPopup {
+    pub fn new(repo_path: PathBuf, event_sender: Sender<ProgramEvent>) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+
+        Self {
+            event_sender,
+            files: Vec::new(),
+            hunk_index: 0,
+            hunks: Vec::new(),
+            position: 0,
+            repo_path,
+            state,
+            visible: false,
+        }
+    }
+
+    pub fn draw<B: Backend>(&mut self, f: &mut Frame<B>, rect: Rect) -> Result<()> {
+        let area = centered_rect(
+            rect.width.saturating_sub(8),
+            rect.height.saturating_sub(4),
+            rect,
+        );
+
+        let container = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(25), Constraint::Percentage(75)].as_ref())
+            .split(area);
+
+        let list_items: Vec<ListItem> = self
+            .files
+            .iter()
+            .map(|file| ListItem::new(Span::raw(file.clone())))
+            .collect();
+
+        let list = TuiList::new(list_items)
+            .block(
+                Block::default()
+                    .title(Span::styled(" Conflicts ", Style::default().fg(Color::Red)))
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
+
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, container[0], &mut self.state);
+
+        let hunk_container = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref())
+            .split(container[1]);
+
+        let header = Paragraph::new(Spans::from(vec![
+            Span::styled(self.hunk_title(), Style::default().fg(Color::Yellow)),
+            Span::raw("  o/t/b ours/theirs/both  O/T whole file  a resolved  c continue"),
+        ]))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        );
+        f.render_widget(header, hunk_container[0]);
+
+        match self.hunks.get(self.hunk_index) {
+            Some(hunk) => {
+                let sides = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(
+                        [
+                            Constraint::Ratio(1, 3),
+                            Constraint::Ratio(1, 3),
+                            Constraint::Ratio(1, 3),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(hunk_container[1]);
+
+                let columns = [
+                    (" Ours ", &hunk.ours, Color::Green),
+                    (" Base ", &hunk.base, Color::Gray),
+                    (" Theirs ", &hunk.theirs, Color::Blue),
+                ];
+
+                for ((title, lines, color), side) in columns.iter().zip(sides.iter()) {
+                    let side_view =
+                        Paragraph::new(Text::styled(lines.join("\n"), Style::default().fg(*color)))
+                            .block(
+                                Block::default()
+                                    .title(*title)
+                                    .borders(Borders::ALL)
+                                    .border_type(BorderType::Rounded),
+                            );
+                    f.render_widget(side_view, *side);
+                }
+            }
+            None => {
+                let message = match self.files.get(self.position) {
+                    Some(_) => "\nNo conflict markers left. Mark the file resolved with a, or take a whole side with O/T.",
+                    None => "\nAll conflicts are resolved. Press c to continue.",
+                };
+                let empty = Paragraph::new(Text::raw(message))
+                    .alignment(tui::layout::Alignment::Center)
+                    .wrap(tui::widgets::Wrap { trim: true })
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded),
+                    );
+                f.render_widget(empty, hunk_container[1]);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    fn hunk_title(&self) -> String {
+        match self.hunks.len() {
+            0 => "No conflicts".to_string(),
+            len => format!("Conflict {}/{}", self.hunk_index + 1, len),
+        }
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        self.files = get_conflicted_paths(&self.repo_path)?;
+
+        if self.position >= self.files.len() {
+            let position = self.files.len().saturating_sub(1);
+            self.set_position(position);
+            self.set_state(position);
+        }
+
+        self.load_hunks()
+    }
+
+    fn load_hunks(&mut self) -> Result<()> {
+        self.hunks = match self.files.get(self.position) {
+            Some(file) => get_conflict_hunks(&self.repo_path, file)?,
+            None => Vec::new(),
+        };
+        self.hunk_index = self.hunk_index.min(self.hunks.len().saturating_sub(1));
+
+        Ok(())
+    }
+
+    fn select_file(&mut self, up: bool) -> Result<()> {
+        if up {
+            self.scroll_up(1);
+        } else {
+            self.scroll_down(1);
+        }
+        self.hunk_index = 0;
+        self.load_hunks()
+    }
+
+    fn select_hunk(&mut self, up: bool) {
+        if up {
+            self.hunk_index = self.hunk_index.saturating_sub(1);
+        } else if self.hunk_index + 1 < self.hunks.len() {
+            self.hunk_index += 1;
+        }
+    }
+
+    fn resolve_hunk(&mut self, resolution: Resolution) -> Result<()> {
+        if let Some(file) = self.files.get(self.position) {
+            if self.hunk_index < self.hunks.len() {
+                let result =
+                    resolve_conflict_hunk(&self.repo_path, file, self.hunk_index, resolution);
+                self.send_result(result);
+            }
+        }
+        self.refresh()
+    }
+
+    fn resolve_file(&mut self, resolution: Resolution) -> Result<()> {
+        if let Some(file) = self.files.get(self.position) {
+            let result = resolve_conflict_file(&self.repo_path, file, resolution);
+            self.send_result(result);
+        }
+        self.refresh()
+    }
+
+    fn mark_resolved(&mut self) -> Result<()> {
+        if let Some(file) = self.files.get(self.position) {
+            let result = mark_resolved(&self.repo_path, file);
+            self.send_result(result);
+        }
+        self.refresh()
+    }
+
+    /// Closes the popup and continues in the background, as the commit
+    /// hooks may take a while.
+    fn continue_operation(&mut self) {
+        self.reset();
+        let repo_path = self.repo_path.clone();
+        let event_sender = self.event_sender.clone();
+
+        thread::spawn(move || {
+            if let Err(err) = continue_operation(&repo_path, &event_sender) {
+                event_sender
+                    .send(ProgramEvent::Error(err))
+                    .expect("Send failed.");
+            }
+            event_sender
+                .send(ProgramEvent::Git(GitEvent::RefreshCommitLog))
+                .expect("Send failed.");
+        });
+    }
+
+    fn send_result(&self, result: Result<()>) {
+        if let Err(err) = result {
+            self.event_sender
+                .send(ProgramEvent::Error(err))
+                .expect("Send failed.");
+        }
+    }
+
+    fn reset(&mut self) {
+        self.event_sender
+            .send(ProgramEvent::Focus(ComponentType::FilesComponent))
+            .expect("Focus event send failed.");
+        self.visible = false;
+    }
+}
+
+impl Component for ConflictPopup {
+    fn update(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn handle_event(&mut self, ev: KeyEvent) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+
+        match ev.code {
+            KeyCode::Char('j') => self.select_file(false)?,
+            KeyCode::Char('k') => self.select_file(true)?,
+            KeyCode::Char('n') => self.select_hunk(false),
+            KeyCode::Char('N') => self.select_hunk(true),
+            KeyCode::Char('o') => self.resolve_hunk(Resolution::Ours)?,
+            KeyCode::Char('t') => self.resolve_hunk(Resolution::Theirs)?,
+            KeyCode::Char('b') => self.resolve_hunk(Resolution::Both)?,
+            KeyCode::Char('O') => self.resolve_file(Resolution::Ours)?,
+            KeyCode::Char('T') => self.resolve_file(Resolution::Theirs)?,
+            KeyCode::Char('a') => self.mark_resolved()?,
+            KeyCode::Char('c') => self.continue_operation(),
+            KeyCode::Esc => self.reset(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn focus(&mut self, focus: bool) {
+        self.visible = focus;
+
+        if focus {
+            self.hunk_index = 0;
+            if let Err(err) = self.refresh() {
+                self.send_result(Err(err));
+            }
+        }
+    }
+}
+
+impl ScrollableComponent for ConflictPopup {
+    fn get_list_length(&self) -> usize {
+        self.files.len()
+    }
+    fn get_position(&self) -> usize {
+        self.position
+    }
+    fn set_position(&mut self, position: usize) {
+        self.position = position;
+    }
+    fn set_state(&mut self, position: usize) {
+        self.state.select(Some(position));
+    }
+}
@@ -1,31 +1,44 @@
 use std::path::PathBuf;
 
 use anyhow::Result;
+use crossbeam::channel::Sender;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use tui::backend::Backend;
 use tui::layout::Rect;
+use tui::style::{Color, Style};
 use tui::text::Span;
 use tui::widgets::{Block, BorderType, Borders, List as TuiList, ListItem, ListState};
 use tui::Frame;
 
+use crate::app::ProgramEvent;
 use crate::component_style::ComponentTheme;
-use crate::components::Component;
+use crate::components::confirm_popup::ConfirmAction;
+use crate::components::{Component, ComponentType};
 use crate::git::diff::{get_diff, DiffComponentType, DiffLine};
+use crate::git::patch::{build_patch, PatchHunk, PatchLine};
+use crate::git::stage::{apply_patch, stage_hunk, unstage_hunk};
+use crate::git::stash::get_stash_diff;
 use crate::list_window::{ListWindow, ScrollDirection};
 
 pub struct DiffComponent {
     diffs: Vec<DiffLine>,
     diff_type: DiffComponentType,
+    event_sender: Sender<ProgramEvent>,
     first_update: bool,
     focused: bool,
     repo_path: PathBuf,
+    selection_anchor: Option<usize>,
     state: ListState,
     style: ComponentTheme,
     window: ListWindow,
 }
 
 impl DiffComponent {
-    pub fn new(repo_path: PathBuf, diff_type: DiffComponentType) -> Self {
+    pub fn new(
+        repo_path: PathBuf,
+        diff_type: DiffComponentType,
+        event_sender: Sender<ProgramEvent>,
+    ) -> Self {
         let diffs = match get_diff(&repo_path, false) {
             Ok(diffs) => diffs,
             Err(err) => vec![DiffLine::err(err)],
@@ -35,9 +48,11 @@ impl DiffComponent {
         Self {
             diffs,
             diff_type,
+            event_sender,
             first_update: true,
             focused: false,
             repo_path,
+            selection_anchor: None,
             state: ListState::default(),
             style: ComponentTheme::default(),
             window: ListWindow::new(0, 0, 0, len, 0),
@@ -47,44 +62,202 @@ impl DiffComponent {
     pub fn draw<B: Backend>(&mut self, f: &mut Frame<B>, rect: Rect) -> Result<()> {
         self.window.set_height(((f.size().height as usize) - 4) / 2);
 
+        let selection = self.selection();
         let list_items: Vec<ListItem> = self
             .diffs
             .iter()
-            .map(|item| {
+            .enumerate()
+            .map(|(index, item)| {
                 let content = match item.origin() {
                     '-' => format!("-{}", item.content()),
                     '+' => format!("+{}", item.content()),
                     _ => format!(" {}", item.content()),
                 };
                 let text = Span::styled(content, item.style());
-                ListItem::new(text)
+
+                match selection {
+                    Some((start, end)) if (start..=end).contains(&index) => {
+                        ListItem::new(text).style(Style::default().bg(Color::Rgb(32, 48, 72)))
+                    }
+                    _ => ListItem::new(text),
+                }
             })
             .collect();
 
         let title = match self.diff_type {
             DiffComponentType::Diff => " Diff ",
             DiffComponentType::Staged => " Staged ",
+            DiffComponentType::Stash(_) => " Stash ",
         };
 
-        let list = TuiList::new(list_items).block(
-            Block::default()
-                .title(title)
-                .style(self.style.style())
-                .borders(Borders::ALL)
-                .border_style(self.style.border_style())
-                .border_type(BorderType::Rounded),
-        );
+        let list = TuiList::new(list_items)
+            .block(
+                Block::default()
+                    .title(title)
+                    .style(self.style.style())
+                    .borders(Borders::ALL)
+                    .border_style(self.style.border_style())
+                    .border_type(BorderType::Rounded),
+            )
+            .highlight_style(Style::default().bg(Color::Rgb(48, 48, 48)));
 
         f.render_stateful_widget(list, rect, &mut self.state);
 
         Ok(())
     }
 
+    /// Switches between the unstaged changes and a read-only stash preview.
+    pub fn set_diff_type(&mut self, diff_type: DiffComponentType) {
+        if diff_type != self.diff_type {
+            self.diff_type = diff_type;
+            self.selection_anchor = None;
+            self.window.reset();
+            self.state.select(self.window.position());
+        }
+    }
+
     fn render_diff(&mut self) {
-        self.window.reset();
+        // Keep the cursor where it was so consecutive hunks can be staged
+        // without scrolling back down each time.
+        let position = self.window.position().unwrap_or(0);
+        self.window.set_size(self.diffs.len());
+        self.window.select(position);
+        self.state.select(self.window.position());
+    }
+
+    fn move_cursor(&mut self, direction: ScrollDirection, amount: usize) {
+        self.window.move_cursor(direction, amount);
         self.state.select(self.window.position());
     }
 
+    fn selected_line(&self) -> Option<&DiffLine> {
+        self.window
+            .position()
+            .and_then(|position| self.diffs.get(position))
+    }
+
+    fn selection(&self) -> Option<(usize, usize)> {
+        let anchor = self.selection_anchor?;
+        let position = self.window.position()?;
+        Some((anchor.min(position), anchor.max(position)))
+    }
+
+    fn toggle_selection(&mut self) {
+        self.selection_anchor = match self.selection_anchor {
+            Some(_) => None,
+            None => self.window.position(),
+        };
+    }
+
+    fn apply(&mut self) {
+        let result = match self.selection() {
+            Some((start, end)) => self.apply_lines(start, end),
+            None => self.apply_hunk(),
+        };
+
+        if let Err(err) = result {
+            self.event_sender
+                .send(ProgramEvent::Error(err))
+                .expect("Send failed.");
+        }
+        self.selection_anchor = None;
+    }
+
+    fn apply_lines(&self, start: usize, end: usize) -> Result<()> {
+        let reverse = self.diff_type == DiffComponentType::Staged;
+        for patch in self.selection_patches(start, end, reverse) {
+            apply_patch(&self.repo_path, &patch)?;
+        }
+
+        Ok(())
+    }
+
+    fn discard(&mut self) {
+        let action = match self.selection() {
+            Some((start, end)) => {
+                ConfirmAction::DiscardLines(self.selection_patches(start, end, true))
+            }
+            None => match self.selected_line() {
+                Some(line) => match line.hunk() {
+                    Some(hunk) => ConfirmAction::DiscardHunk(line.file().clone(), hunk),
+                    None => return,
+                },
+                None => return,
+            },
+        };
+        self.selection_anchor = None;
+
+        self.event_sender
+            .send(ProgramEvent::Focus(ComponentType::ConfirmComponent(action)))
+            .expect("Send failed.");
+    }
+
+    /// Builds one patch per file containing only the selected lines.
+    fn selection_patches(&self, start: usize, end: usize, reverse: bool) -> Vec<String> {
+        // Group the lines of every hunk the selection touches by file
+        let mut files: Vec<(&str, Vec<PatchHunk>)> = Vec::new();
+        let mut index = 0;
+
+        while index < self.diffs.len() {
+            let line = &self.diffs[index];
+            let hunk = match line.hunk() {
+                Some(hunk) if line.origin() != 'H' => hunk,
+                _ => {
+                    index += 1;
+                    continue;
+                }
+            };
+
+            let file = line.file().as_str();
+            let mut lines = Vec::new();
+
+            while let Some(line) = self.diffs.get(index) {
+                if line.hunk() != Some(hunk) || line.file() != file || line.origin() == 'H' {
+                    break;
+                }
+
+                lines.push(PatchLine {
+                    origin: line.origin(),
+                    content: line.content(),
+                    selected: (start..=end).contains(&index),
+                });
+                index += 1;
+            }
+
+            if !lines.iter().any(|line| line.selected) {
+                continue;
+            }
+
+            let hunk = PatchHunk {
+                header: hunk,
+                lines,
+            };
+
+            match files.last_mut() {
+                Some((last_file, hunks)) if *last_file == file => hunks.push(hunk),
+                _ => files.push((file, vec![hunk])),
+            }
+        }
+
+        files
+            .iter()
+            .filter_map(|(file, hunks)| build_patch(file, hunks, reverse))
+            .collect()
+    }
+
+    fn apply_hunk(&self) -> Result<()> {
+        if let Some(line) = self.selected_line() {
+            if let Some(hunk) = line.hunk() {
+                match self.diff_type {
+                    DiffComponentType::Staged => unstage_hunk(&self.repo_path, line.file(), hunk)?,
+                    _ => stage_hunk(&self.repo_path, line.file(), hunk)?,
+                };
+            }
+        }
+
+        Ok(())
+    }
+
     fn scroll_up(&mut self, amount: usize) {
         self.window.scroll(ScrollDirection::Up, amount);
         self.state.select(self.window.position());
@@ -105,16 +278,15 @@ impl Component for DiffComponent {
 
         let path = &self.repo_path;
 
-        let diff = if self.diff_type == DiffComponentType::Staged {
-            get_diff(path, true)?
-        } else {
-            get_diff(path, false)?
+        let diff = match &self.diff_type {
+            DiffComponentType::Diff => get_diff(path, false)?,
+            DiffComponentType::Staged => get_diff(path, true)?,
+            DiffComponentType::Stash(stash) => get_stash_diff(path, stash)?,
         };
 
-        if diff.len() != self.diffs.len() {
-            self.render_diff();
+        if diff != self.diffs {
             self.diffs = diff;
-            self.window.set_size(self.diffs.len());
+            self.render_diff();
         }
         Ok(())
     }
@@ -125,10 +297,10 @@ impl Component for DiffComponent {
         }
         match ev.code {
             KeyCode::Char('j') => {
-                self.scroll_down(1);
+                self.move_cursor(ScrollDirection::Down, 1);
             }
             KeyCode::Char('k') => {
-                self.scroll_up(1);
+                self.move_cursor(ScrollDirection::Up, 1);
             }
             KeyCode::Char('d') if ev.modifiers == KeyModifiers::CONTROL => {
                 let height = self.window.height();
@@ -138,6 +310,18 @@ impl Component for DiffComponent {
                 let height = self.window.height();
                 self.scroll_up(height / 2);
             }
+            KeyCode::Char('d') if self.diff_type == DiffComponentType::Diff => {
+                self.discard();
+            }
+            KeyCode::Char('v') => {
+                self.toggle_selection();
+            }
+            KeyCode::Char('s') if self.diff_type == DiffComponentType::Diff => {
+                self.apply();
+            }
+            KeyCode::Char('u') if self.diff_type == DiffComponentType::Staged => {
+                self.apply();
+            }
             _ => {}
         }
         Ok(())
@@ -52,7 +52,7 @@ impl ErrorComponent {
             )
             .split(area);
 
-        let message = Paragraph::new(Span::raw(&self.message))
+        let message = Paragraph::new(Text::from(self.message.as_str()))
             .alignment(tui::layout::Alignment::Center)
             .style(Style::default().fg(Color::White))
             .wrap(tui::widgets::Wrap { trim: true });
@@ -74,6 +74,14 @@ impl ErrorComponent {
         self.message = message;
     }
 
+    /// Adds another message below the ones already shown.
+    pub fn push_message(&mut self, message: String) {
+        if !self.message.is_empty() {
+            self.message.push('\n');
+        }
+        self.message.push_str(&message);
+    }
+
     fn reset(&mut self) {
         self.event_sender
             .send(ProgramEvent::Focus(ComponentType::FilesComponent))
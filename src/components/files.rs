@@ -1,4 +1,5 @@
 use std::path::PathBuf;
+use std::thread;
 
 use anyhow::Result;
 use crossbeam::channel::Sender;
@@ -10,12 +11,15 @@ use tui::text::Span;
 use tui::widgets::{Block, BorderType, Borders, List as TuiList, ListItem, ListState};
 use tui::Frame;
 
-use crate::app::ProgramEvent;
+use crate::app::{GitEvent, ProgramEvent};
 use crate::component_style::ComponentTheme;
+use crate::components::confirm_popup::ConfirmAction;
 use crate::components::{Component, ComponentType, ScrollableComponent};
+use crate::git::blame::BlameTarget;
 use crate::git::remote::{get_remote, push};
 use crate::git::stage::{stage_all, stage_file, unstage_all, unstage_file};
-use crate::git::status::{get_file_status, FileStatus, StatusLoc, StatusType};
+use crate::git::state::{continue_operation, get_state_label};
+use crate::git::status::{get_file_status, is_untracked, FileStatus, StatusLoc, StatusType};
 use crate::InputLock;
 
 pub struct FileComponent {
@@ -86,6 +90,12 @@ impl FileComponent {
         }
     }
 
+    fn amend(&self) {
+        self.event_sender
+            .send(ProgramEvent::Focus(ComponentType::AmendComponent))
+            .expect("Send Failed");
+    }
+
     fn commit_full(&self) {
         self.input_lock.lock.send(()).expect("Failed to send.");
 
@@ -126,6 +136,99 @@ impl FileComponent {
         self.input_lock.unparker.unpark();
     }
 
+    fn discard_file(&self) -> Result<()> {
+        if let Some(file) = self.files.get(self.position) {
+            if file.status_type == StatusType::Unmodified {
+                return Ok(());
+            }
+
+            let action = if is_untracked(&self.repo_path, &file.path)? {
+                ConfirmAction::DeleteUntrackedFile(file.path.clone())
+            } else {
+                ConfirmAction::DiscardFile(file.path.clone())
+            };
+
+            self.event_sender
+                .send(ProgramEvent::Focus(ComponentType::ConfirmComponent(action)))
+                .expect("Send Failed");
+        }
+
+        Ok(())
+    }
+
+    fn switch_identity(&self) {
+        self.event_sender
+            .send(ProgramEvent::Focus(ComponentType::IdentityComponent))
+            .expect("Send Failed");
+    }
+
+    /// Shows the history of the selected file in the log.
+    fn show_history(&self) {
+        if let Some(file) = self.files.get(self.position) {
+            if file.status_type == StatusType::Unmodified {
+                return;
+            }
+
+            self.event_sender
+                .send(ProgramEvent::Focus(ComponentType::PathLogComponent(
+                    file.path.clone(),
+                )))
+                .expect("Send Failed");
+        }
+    }
+
+    fn blame_file(&self) {
+        if let Some(file) = self.files.get(self.position) {
+            if file.status_type == StatusType::Unmodified {
+                return;
+            }
+
+            self.event_sender
+                .send(ProgramEvent::Focus(ComponentType::BlameComponent(
+                    BlameTarget {
+                        path: file.path.clone(),
+                        commit: None,
+                    },
+                )))
+                .expect("Send Failed");
+        }
+    }
+
+    fn show_conflicts(&self) {
+        self.event_sender
+            .send(ProgramEvent::Focus(ComponentType::ConflictComponent))
+            .expect("Send Failed");
+    }
+
+    /// Continues in the background, as the commit hooks may take a while.
+    fn continue_operation(&self) {
+        let repo_path = self.repo_path.clone();
+        let event_sender = self.event_sender.clone();
+
+        thread::spawn(move || {
+            if let Err(err) = continue_operation(&repo_path, &event_sender) {
+                event_sender
+                    .send(ProgramEvent::Error(err))
+                    .expect("Send Failed");
+            }
+            event_sender
+                .send(ProgramEvent::Git(GitEvent::RefreshCommitLog))
+                .expect("Send Failed");
+        });
+    }
+
+    fn abort_operation(&self) -> Result<()> {
+        if let Some(state) = get_state_label(&self.repo_path)? {
+            self.event_sender
+                .send(ProgramEvent::Focus(ComponentType::ConfirmComponent(
+                    ConfirmAction::AbortOperation(state.to_string()),
+                )))
+                .expect("Send Failed");
+        }
+
+        Ok(())
+    }
+
     fn has_files_staged(&self) -> bool {
         self.files.iter().any(|file| {
             file.status_type == StatusType::IndexModified
@@ -201,6 +304,14 @@ impl Component for FileComponent {
             KeyCode::Char('u') => self.unstage_file(false)?,
             KeyCode::Char('c') => self.commit(),
             KeyCode::Char('C') => self.commit_full(),
+            KeyCode::Char('e') => self.amend(),
+            KeyCode::Char('d') => self.discard_file()?,
+            KeyCode::Char('b') => self.blame_file(),
+            KeyCode::Char('i') => self.switch_identity(),
+            KeyCode::Char('l') => self.show_history(),
+            KeyCode::Char('m') => self.show_conflicts(),
+            KeyCode::Char('r') => self.continue_operation(),
+            KeyCode::Char('x') => self.abort_operation()?,
             KeyCode::Char('p') => self.push()?,
             _ => {}
         }
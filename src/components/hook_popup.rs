@@ -0,0 +1,132 @@
+use anyhow::Result;
+use crossbeam::channel::Sender;
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, BorderType, Borders, Clear, Paragraph};
+use tui::Frame;
+
+use crate::app::ProgramEvent;
+use crate::components::{centered_rect, Component, ComponentType};
+
+/// Shows the output of git hooks as they run.
+pub struct HookPopup {
+    event_sender: Sender<ProgramEvent>,
+    /// Follow new output until scrolled up.
+    follow: bool,
+    height: usize,
+    hook: Option<String>,
+    lines: Vec<Spans<'static>>,
+    /// Where to go back to when the popup is closed.
+    origin: ComponentType,
+    scroll: usize,
+    visible: bool,
+}
+
+impl HookPopup {
+    pub fn new(event_sender: Sender<ProgramEvent>) -> Self {
+        Self {
+            event_sender,
+            follow: true,
+            height: 0,
+            hook: None,
+            lines: Vec::new(),
+            origin: ComponentType::FilesComponent,
+            scroll: 0,
+            visible: false,
+        }
+    }
+
+    pub fn draw<B: Backend>(&mut self, f: &mut Frame<B>, rect: Rect) -> Result<()> {
+        let area = centered_rect(
+            rect.width.saturating_sub(8),
+            rect.height.saturating_sub(4),
+            rect,
+        );
+
+        self.height = area.height.saturating_sub(2) as usize;
+        if self.follow {
+            self.scroll = self.max_scroll();
+        }
+
+        let output = Paragraph::new(self.lines.clone())
+            .scroll((self.scroll as u16, 0))
+            .block(
+                Block::default()
+                    .title(" Hooks ")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            );
+
+        f.render_widget(Clear, area);
+        f.render_widget(output, area);
+        Ok(())
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Adds a line of output, starting a new section when another hook runs.
+    pub fn push_line(&mut self, hook: String, line: String) {
+        if self.hook.as_ref() != Some(&hook) {
+            self.lines.push(Spans::from(Span::styled(
+                format!("{}:", hook),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )));
+            self.hook = Some(hook);
+        }
+        self.lines.push(Spans::from(line));
+    }
+
+    /// Goes back to `origin` when closed, such as the commit popup whose
+    /// `prepare-commit-msg` hook printed something.
+    pub fn set_origin(&mut self, origin: ComponentType) {
+        self.origin = origin;
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.lines.len().saturating_sub(self.height)
+    }
+
+    fn reset(&mut self) {
+        self.event_sender
+            .send(ProgramEvent::Focus(self.origin.clone()))
+            .expect("Focus event send failed.");
+        self.follow = true;
+        self.hook = None;
+        self.lines.clear();
+        self.scroll = 0;
+        self.visible = false;
+    }
+}
+
+impl Component for HookPopup {
+    fn handle_event(&mut self, ev: KeyEvent) -> Result<()> {
+        match ev.code {
+            KeyCode::Char('j') => {
+                self.scroll = (self.scroll + 1).min(self.max_scroll());
+                self.follow = self.scroll == self.max_scroll();
+            }
+            KeyCode::Char('k') => {
+                self.scroll = self.scroll.saturating_sub(1);
+                self.follow = false;
+            }
+            KeyCode::Esc => self.reset(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn focus(&mut self, focus: bool) {
+        self.visible = focus;
+    }
+
+    fn update(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use crossbeam::channel::Sender;
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans, Text};
+use tui::widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph};
+use tui::Frame;
+
+use crate::app::ProgramEvent;
+use crate::components::{centered_rect, Component, ComponentType};
+use crate::git::identity::{
+    current_identity, identity_profiles, set_identity, CurrentIdentity, IdentityProfile,
+};
+
+pub struct IdentityPopup {
+    current: Option<CurrentIdentity>,
+    event_sender: Sender<ProgramEvent>,
+    profiles: Vec<IdentityProfile>,
+    repo_path: PathBuf,
+    state: ListState,
+    visible: bool,
+}
+
+impl IdentityPopup {
+    pub fn new(repo_path: PathBuf, event_sender: Sender<ProgramEvent>) -> Self {
+        Self {
+            current: None,
+            event_sender,
+            profiles: Vec::new(),
+            repo_path,
+            state: ListState::default(),
+            visible: false,
+        }
+    }
+
+    pub fn draw<B: Backend>(&mut self, f: &mut Frame<B>, rect: Rect) -> Result<()> {
+        let list_height = self.profiles.len().max(2) as u16;
+        let area = centered_rect(70, list_height + 5, rect);
+
+        let border = Block::default()
+            .title(Span::raw(" Identity "))
+            .style(Style::default())
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+
+        let container = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Length(2), Constraint::Min(1)].as_ref())
+            .split(area);
+
+        let current = match &self.current {
+            Some(CurrentIdentity {
+                name: Some(name),
+                email: Some(email),
+                source,
+            }) => Spans::from(vec![
+                Span::raw(" Committing as "),
+                Span::styled(
+                    format!("{} <{}>", name, email),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::raw(format!(" from {}", source)),
+            ]),
+            _ => Spans::from(Span::styled(
+                " No identity is set, commits will be refused",
+                Style::default().fg(Color::Red),
+            )),
+        };
+
+        f.render_widget(Clear, area);
+        f.render_widget(border, area);
+        f.render_widget(Paragraph::new(Text::from(current)), container[0]);
+
+        if self.profiles.is_empty() {
+            let hint = Paragraph::new(Text::raw(
+                " Add identities to switch between with\n git config buddy.identity.<name>.name and .email",
+            ))
+            .style(Style::default().fg(Color::Gray));
+            f.render_widget(hint, container[1]);
+            return Ok(());
+        }
+
+        let items: Vec<ListItem> = self
+            .profiles
+            .iter()
+            .map(|profile| {
+                let mut spans = vec![
+                    Span::styled(
+                        format!("{:<10} ", profile.label),
+                        Style::default().fg(Color::Blue),
+                    ),
+                    Span::raw(format!(
+                        "{} <{}>",
+                        profile.identity.name, profile.identity.email
+                    )),
+                ];
+                if let Some(key) = &profile.identity.signing_key {
+                    spans.push(Span::styled(
+                        format!("  key {}", key),
+                        Style::default().fg(Color::Gray),
+                    ));
+                }
+                ListItem::new(Spans::from(spans))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
+
+        f.render_stateful_widget(list, container[1], &mut self.state);
+        Ok(())
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    fn load(&mut self) -> Result<()> {
+        self.current = Some(current_identity(&self.repo_path)?);
+        self.profiles = identity_profiles(&self.repo_path)?;
+        self.state.select(match self.profiles.is_empty() {
+            true => None,
+            false => Some(0),
+        });
+        Ok(())
+    }
+
+    fn select(&mut self) {
+        let profile = match self.state.selected().and_then(|i| self.profiles.get(i)) {
+            Some(profile) => profile.clone(),
+            None => return,
+        };
+
+        self.reset();
+        if let Err(err) = set_identity(&self.repo_path, &profile) {
+            self.event_sender
+                .send(ProgramEvent::Error(err))
+                .expect("Send failed.");
+        }
+    }
+
+    fn reset(&mut self) {
+        self.event_sender
+            .send(ProgramEvent::Focus(ComponentType::FilesComponent))
+            .expect("Focus event send failed.");
+        self.visible = false;
+    }
+}
+
+impl Component for IdentityPopup {
+    fn handle_event(&mut self, ev: KeyEvent) -> Result<()> {
+        let selected = self.state.selected().unwrap_or(0);
+
+        match ev.code {
+            KeyCode::Char('j') if selected + 1 < self.profiles.len() => {
+                self.state.select(Some(selected + 1))
+            }
+            KeyCode::Char('k') if selected > 0 => self.state.select(Some(selected - 1)),
+            KeyCode::Enter => self.select(),
+            KeyCode::Esc => self.reset(),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn focus(&mut self, focus: bool) {
+        if focus && !self.visible {
+            if let Err(err) = self.load() {
+                self.event_sender
+                    .send(ProgramEvent::Error(err))
+                    .expect("Send failed.");
+            }
+        }
+        self.visible = focus;
+    }
+
+    fn update(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::thread;
 
 use anyhow::Result;
 use crossbeam::channel::Sender;
@@ -7,30 +9,81 @@ use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
 use tui::backend::Backend;
 use tui::layout::{Constraint, Direction, Layout, Rect};
-use tui::style::{Color, Style};
+use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::widgets::{Block, BorderType, Borders, List as TuiList, ListItem, ListState, Paragraph};
 use tui::Frame;
 
-use crate::app::ProgramEvent;
+use crate::app::{GitEvent, ProgramEvent};
 use crate::component_style::ComponentTheme;
 use crate::components::{Component, ComponentType, ScrollableComponent};
 use crate::git::branch::checkout_local_branch;
 use crate::git::commit::revert_commit;
-use crate::git::log::{collect_commits, Commit};
-use crate::git::repo;
+use crate::git::graph::{GraphBuilder, GraphCell};
+use crate::git::log::{
+    page_commits, refs_fingerprint, Commit, LogScope, PathFilter, RefKind, RefLabel,
+};
+use crate::git::rebase::{
+    get_rebase_todo, reworded_message, start_rebase, RebaseAction, RebaseTodo,
+};
+use crate::git::search::{has_qualifiers, search_commits, LogQuery};
+use crate::git::sign::{verify_commit, SignatureStatus};
+
+/// How many commits are loaded at a time.
+const PAGE_SIZE: usize = 200;
+
+/// Graph lanes cycle through these colours.
+const LANE_COLORS: [Color; 6] = [
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Yellow,
+    Color::Green,
+    Color::Red,
+];
 
 pub struct LogComponent {
+    /// Whether every branch and tag is walked, not only HEAD.
+    all_refs: bool,
     event_sender: Sender<ProgramEvent>,
     filtered_commits: Vec<Commit>,
+    /// Whether the whole history has been loaded.
+    finished: bool,
     focused: bool,
+    /// Bumped on every reload, so pages of an older walk are ignored.
+    generation: usize,
+    /// The graph row of each commit in `commits`.
+    graph: Vec<Vec<GraphCell>>,
+    graph_builder: GraphBuilder,
     input: String,
+    is_entering_path: bool,
     is_searching: bool,
+    is_rewording: bool,
     commits: Vec<Commit>,
+    loaded_generation: usize,
+    loading: bool,
+    /// Asks the background walk for more commits.
+    pages: Option<Sender<usize>>,
+    /// Only commits changing this file or directory are shown.
+    path_filter: Option<PathFilter>,
     position: usize,
+    rebase_base: Option<String>,
+    rebase_todo: Vec<RebaseTodo>,
+    /// Where HEAD and the refs pointed when the log was last loaded.
+    refs_fingerprint: Option<u64>,
     repo_path: PathBuf,
+    /// Keeps the background search of the history running.
+    search: Option<Sender<()>>,
+    /// Bumped on every search, so results of an older one are ignored.
+    search_generation: usize,
+    search_running: bool,
+    /// The search whose results are shown, when it had qualifiers.
+    searched: Option<String>,
+    /// Verified signatures by commit id, checking them is slow.
+    signatures: HashMap<String, SignatureStatus>,
     state: ListState,
     style: ComponentTheme,
+    verifying: HashSet<String>,
 }
 
 impl LogComponent {
@@ -39,24 +92,67 @@ impl LogComponent {
         state.select(Some(0));
 
         Self {
+            all_refs: false,
             event_sender,
             filtered_commits: Vec::new(),
+            finished: false,
             focused: false,
+            generation: 0,
+            graph: Vec::new(),
+            graph_builder: GraphBuilder::new(),
             input: String::new(),
+            is_entering_path: false,
             is_searching: false,
+            is_rewording: false,
             commits: Vec::new(),
+            loaded_generation: 0,
+            loading: false,
+            pages: None,
+            path_filter: None,
             position: 0,
+            rebase_base: None,
+            rebase_todo: Vec::new(),
+            refs_fingerprint: None,
             repo_path,
+            search: None,
+            search_generation: 0,
+            search_running: false,
+            searched: None,
+            signatures: HashMap::new(),
             state,
             style: ComponentTheme::default(),
+            verifying: HashSet::new(),
         }
     }
 
     pub fn draw<B: Backend>(&mut self, f: &mut Frame<B>, rect: Rect) -> Result<()> {
-        let input_constraint = if self.is_searching { 3 } else { 0 };
+        let input_constraint = if self.is_searching || self.is_rewording || self.is_entering_path {
+            3
+        } else {
+            0
+        };
+
+        let title = match &self.rebase_base {
+            Some(base) => format!(" Rebase onto {} ", &base[0..8]),
+            None => {
+                let mut title = " Log".to_string();
+                if self.all_refs {
+                    title.push_str(" (all refs)");
+                }
+                match &self.path_filter {
+                    Some(paths) if paths.follow => {
+                        title.push_str(&format!(": {} (following renames)", paths.path))
+                    }
+                    Some(paths) => title.push_str(&format!(": {}", paths.path)),
+                    None => {}
+                }
+                title.push(' ');
+                title
+            }
+        };
 
         let log_block = Block::default()
-            .title(" Log ")
+            .title(title)
             .style(self.style.style())
             .borders(Borders::ALL)
             .border_style(self.style.border_style())
@@ -69,27 +165,80 @@ impl LogComponent {
             .margin(1)
             .split(rect);
 
+        let search_status = match &self.searched {
+            _ if self.is_entering_path => " Path to show the history of ".to_string(),
+            _ if !self.is_filtering() || !has_qualifiers(&self.input[1..]) => String::new(),
+            Some(query) if *query == self.input && self.search_running => {
+                " Searching the history... ".to_string()
+            }
+            Some(query) if *query == self.input => {
+                format!(" {} found ", self.filtered_commits.len())
+            }
+            _ => " Enter to search the history ".to_string(),
+        };
+
         let input = Paragraph::new(self.input.as_ref())
             .style(Style::default())
             .block(
                 Block::default()
+                    .title(search_status)
                     .borders(Borders::ALL)
                     .border_style(self.style.border_style())
                     .border_type(BorderType::Rounded),
             );
 
-        let list_items: Vec<ListItem> = self
-            .filtered_commits
-            .iter()
-            .map(|item| {
-                let text = Spans::from(vec![
-                    Span::styled(item.shorthand_id(), Style::default().fg(Color::Green)),
-                    Span::raw(" "),
-                    Span::raw(item.message_summary()),
-                ]);
-                ListItem::new(text)
-            })
-            .collect();
+        let list_items: Vec<ListItem> = if self.rebase_base.is_some() {
+            self.rebase_todo
+                .iter()
+                .map(|entry| {
+                    let style = match entry.action {
+                        RebaseAction::Drop => Style::default().fg(Color::Red),
+                        RebaseAction::Pick => Style::default(),
+                        _ => Style::default().fg(Color::Yellow),
+                    };
+                    let summary = match &entry.message {
+                        Some(message) => message.lines().next().unwrap_or("").to_string(),
+                        None => entry.summary.clone(),
+                    };
+
+                    ListItem::new(Spans::from(vec![
+                        Span::styled(
+                            format!("{:<7}", entry.action.name()),
+                            style.add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(&entry.id[0..8], Style::default().fg(Color::Green)),
+                        Span::raw(" "),
+                        Span::styled(summary, style),
+                    ]))
+                })
+                .collect()
+        } else {
+            // The graph only makes sense for the whole history
+            let graph = match self.is_filtering() || self.path_filter.is_some() {
+                true => &[],
+                false => self.graph.as_slice(),
+            };
+
+            self.filtered_commits
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    let mut text = graph
+                        .get(index)
+                        .map(|row| graph_spans(row))
+                        .unwrap_or_default();
+                    text.extend([
+                        signature_span(item.signature()),
+                        Span::raw(" "),
+                        Span::styled(item.shorthand_id(), Style::default().fg(Color::Green)),
+                        Span::raw(" "),
+                    ]);
+                    text.extend(ref_spans(item.refs()));
+                    text.push(Span::raw(item.message_summary()));
+                    ListItem::new(Spans::from(text))
+                })
+                .collect()
+        };
 
         let list = TuiList::new(list_items)
             .block(Block::default().style(self.style.style()))
@@ -102,6 +251,195 @@ impl LogComponent {
         Ok(())
     }
 
+    fn toggle_all_refs(&mut self) {
+        self.all_refs = !self.all_refs;
+        self.reset_state();
+        self.reload();
+
+        if self.searched.as_ref() == Some(&self.input) {
+            self.run_search();
+        }
+    }
+
+    /// Shows only the commits changing a path, or every commit again.
+    pub fn set_path_filter(&mut self, path_filter: Option<PathFilter>) {
+        self.path_filter = path_filter;
+        self.reset_state();
+        self.reload();
+
+        if self.searched.as_ref() == Some(&self.input) {
+            self.run_search();
+        }
+    }
+
+    fn toggle_follow_renames(&mut self) {
+        if let Some(mut path_filter) = self.path_filter.clone() {
+            path_filter.follow = !path_filter.follow;
+            self.set_path_filter(Some(path_filter));
+        }
+    }
+
+    fn enter_path(&mut self) {
+        self.close_search();
+        self.is_entering_path = true;
+        self.input = match &self.path_filter {
+            Some(path_filter) => path_filter.path.clone(),
+            None => String::new(),
+        };
+    }
+
+    /// Filters by the path typed, keeping whether renames are followed. No
+    /// path shows every commit again.
+    fn finish_path(&mut self) {
+        let path = std::mem::take(&mut self.input);
+        let path = path.trim().trim_start_matches("./");
+        self.is_entering_path = false;
+
+        let path_filter = match &self.path_filter {
+            _ if path.is_empty() => None,
+            Some(path_filter) => Some(PathFilter {
+                path: path.to_string(),
+                follow: path_filter.follow,
+            }),
+            None => Some(PathFilter::new(path.to_string())),
+        };
+        self.set_path_filter(path_filter);
+    }
+
+    fn handle_path_event(&mut self, ev: KeyEvent) {
+        match ev.code {
+            KeyCode::Char(c) => self.input.push(c),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Enter => self.finish_path(),
+            KeyCode::Esc => {
+                self.input.clear();
+                self.is_entering_path = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn scope(&self) -> LogScope {
+        match self.all_refs {
+            true => LogScope::AllRefs,
+            false => LogScope::Head,
+        }
+    }
+
+    /// Starts walking the history again from the top. The commits shown are
+    /// kept until the first page arrives.
+    fn reload(&mut self) {
+        self.generation += 1;
+        self.loading = false;
+        self.finished = false;
+        self.pages = Some(page_commits(
+            self.repo_path.clone(),
+            self.scope(),
+            self.path_filter.clone(),
+            self.generation,
+            self.event_sender.clone(),
+        ));
+        self.load_page(PAGE_SIZE.max(self.position + PAGE_SIZE));
+    }
+
+    fn load_page(&mut self, count: usize) {
+        if self.loading || self.finished {
+            return;
+        }
+        if let Some(pages) = &self.pages {
+            self.loading = pages.send(count).is_ok();
+        }
+    }
+
+    /// Adds a page of commits from the background walk.
+    pub fn add_commits(&mut self, generation: usize, commits: Vec<Commit>, finished: bool) {
+        if generation != self.generation {
+            return;
+        }
+        if generation != self.loaded_generation {
+            self.loaded_generation = generation;
+            self.commits.clear();
+            self.graph.clear();
+            self.graph_builder = GraphBuilder::new();
+        }
+
+        self.loading = false;
+        self.finished = finished;
+        for commit in commits {
+            self.graph
+                .push(self.graph_builder.row(commit.id(), commit.parents()));
+            self.commits.push(commit);
+        }
+        self.verify_signatures();
+        self.filter();
+    }
+
+    fn is_filtering(&self) -> bool {
+        self.is_searching && self.input.len() > 1
+    }
+
+    /// Fuzzy finds the search among the commits loaded. Searches with
+    /// qualifiers are left alone, they go through the whole history when
+    /// `Enter` is pressed.
+    fn filter(&mut self) {
+        if !self.is_filtering() {
+            self.filtered_commits = self.commits.clone();
+        } else if !has_qualifiers(&self.input[1..]) {
+            self.filtered_commits = fuzzy_find(&self.commits, &self.input[1..]);
+        }
+    }
+
+    fn should_search_history(&self) -> bool {
+        self.is_filtering()
+            && has_qualifiers(&self.input[1..])
+            && self.searched.as_ref() != Some(&self.input)
+    }
+
+    /// Searches the whole history in the background, the results replacing
+    /// the commits shown as they come in.
+    fn run_search(&mut self) {
+        let query = match LogQuery::parse(&self.input[1..]) {
+            Ok(query) => query,
+            Err(err) => {
+                self.send_error(err);
+                return;
+            }
+        };
+
+        self.search_generation += 1;
+        self.search_running = true;
+        self.searched = Some(self.input.clone());
+        self.filtered_commits.clear();
+        self.reset_state();
+        // Replacing the old search stops it
+        self.search = Some(search_commits(
+            self.repo_path.clone(),
+            self.scope(),
+            self.path_filter.clone(),
+            query,
+            self.search_generation,
+            self.event_sender.clone(),
+        ));
+    }
+
+    pub fn add_search_results(&mut self, generation: usize, commits: Vec<Commit>, finished: bool) {
+        if generation != self.search_generation || self.searched.is_none() {
+            return;
+        }
+
+        self.search_running = !finished;
+        self.filtered_commits.extend(commits);
+        self.verify_signatures();
+    }
+
+    fn stop_search(&mut self) {
+        self.search = None;
+        self.search_running = false;
+        self.searched = None;
+    }
+
     fn checkout_local_branch(&self) -> Result<()> {
         if let Some(commit) = self.filtered_commits.get(self.position) {
             checkout_local_branch(&self.repo_path, commit.id())?;
@@ -125,21 +463,214 @@ impl LogComponent {
         self.reset_state();
 
         if self.input.is_empty() {
-            self.is_searching = false;
+            self.close_search();
         } else {
-            self.filtered_commits = fuzzy_find(&self.commits, &self.input[1..]);
+            self.filter();
         }
     }
 
     fn push_char(&mut self, c: char) {
         self.input.push(c);
-        self.filtered_commits = fuzzy_find(&self.commits, &self.input[1..]);
+        self.filter();
         self.reset_state();
     }
 
+    fn close_search(&mut self) {
+        self.input.clear();
+        self.is_searching = false;
+        self.stop_search();
+        self.filter();
+        self.reset_state();
+    }
+
+    /// Whether `Esc` is used by the log, rather than quitting.
+    pub fn is_editing(&self) -> bool {
+        self.is_searching
+            || self.is_entering_path
+            || self.rebase_base.is_some()
+            || (self.focused && self.path_filter.is_some())
+    }
+
+    /// Starts building a rebase of the commits above the selected one.
+    fn rebase_mode(&mut self) {
+        if let Some(commit) = self.filtered_commits.get(self.position) {
+            match get_rebase_todo(&self.repo_path, commit.id()) {
+                Ok(todo) if todo.is_empty() => self.send_error(anyhow::Error::msg(
+                    "There are no commits above this one to rebase",
+                )),
+                Ok(todo) => {
+                    self.rebase_base = Some(commit.id().clone());
+                    self.rebase_todo = todo;
+                    self.close_search();
+                }
+                Err(err) => self.send_error(err),
+            }
+        }
+    }
+
+    fn exit_rebase_mode(&mut self) {
+        self.rebase_base = None;
+        self.rebase_todo.clear();
+        self.is_rewording = false;
+        self.input.clear();
+        self.reset_state();
+    }
+
+    fn set_rebase_action(&mut self, action: RebaseAction) {
+        if let Some(entry) = self.rebase_todo.get_mut(self.position) {
+            entry.action = action;
+
+            if action == RebaseAction::Reword {
+                self.input = match &entry.message {
+                    Some(message) => message.lines().next().unwrap_or("").to_string(),
+                    None => entry.summary.clone(),
+                };
+                self.is_rewording = true;
+            }
+        }
+    }
+
+    fn move_rebase_entry(&mut self, up: bool) {
+        let position = self.position;
+        if up && position > 0 {
+            self.rebase_todo.swap(position, position - 1);
+            self.scroll_up(1);
+        } else if !up && position + 1 < self.rebase_todo.len() {
+            self.rebase_todo.swap(position, position + 1);
+            self.scroll_down(1);
+        }
+    }
+
+    /// Replaces the summary of the commit being reworded, keeping its body.
+    fn finish_reword(&mut self) {
+        let summary = std::mem::take(&mut self.input);
+        self.is_rewording = false;
+
+        let id = match self.rebase_todo.get(self.position) {
+            Some(entry) => entry.id.clone(),
+            None => return,
+        };
+        match reworded_message(&self.repo_path, &id, &summary) {
+            Ok(message) => self.rebase_todo[self.position].message = Some(message),
+            Err(err) => self.send_error(err),
+        }
+    }
+
+    fn start_rebase(&mut self) {
+        if let Some(base) = self.rebase_base.clone() {
+            if let Err(err) = start_rebase(
+                &self.repo_path,
+                &base,
+                &self.rebase_todo,
+                &self.event_sender,
+            ) {
+                self.send_error(err);
+            }
+        }
+        self.exit_rebase_mode();
+    }
+
+    fn handle_rebase_event(&mut self, ev: KeyEvent) {
+        match ev.code {
+            KeyCode::Char(c) if self.is_rewording => self.input.push(c),
+            KeyCode::Backspace if self.is_rewording => {
+                self.input.pop();
+            }
+            KeyCode::Enter if self.is_rewording => self.finish_reword(),
+            KeyCode::Esc if self.is_rewording => {
+                self.input.clear();
+                self.is_rewording = false;
+            }
+            KeyCode::Char('j') => self.scroll_down(1),
+            KeyCode::Char('k') => self.scroll_up(1),
+            KeyCode::Char('J') => self.move_rebase_entry(false),
+            KeyCode::Char('K') => self.move_rebase_entry(true),
+            KeyCode::Char('p') => self.set_rebase_action(RebaseAction::Pick),
+            KeyCode::Char('r') => self.set_rebase_action(RebaseAction::Reword),
+            KeyCode::Char('e') => self.set_rebase_action(RebaseAction::Edit),
+            KeyCode::Char('s') => self.set_rebase_action(RebaseAction::Squash),
+            KeyCode::Char('f') => self.set_rebase_action(RebaseAction::Fixup),
+            KeyCode::Char('d') => self.set_rebase_action(RebaseAction::Drop),
+            KeyCode::Enter => self.start_rebase(),
+            KeyCode::Esc => self.exit_rebase_mode(),
+            _ => {}
+        }
+    }
+
+    fn send_error(&self, err: anyhow::Error) {
+        self.event_sender
+            .send(ProgramEvent::Error(err))
+            .expect("Send failed.");
+    }
+
+    /// Fills in the signatures already verified and starts verifying the rest
+    /// in the background.
+    fn verify_signatures(&mut self) {
+        let mut unchecked = Vec::new();
+
+        // Search results can be further back than the commits loaded
+        for commit in self
+            .commits
+            .iter_mut()
+            .chain(self.filtered_commits.iter_mut())
+        {
+            if *commit.signature() != SignatureStatus::Unchecked {
+                continue;
+            }
+            match self.signatures.get(commit.id()) {
+                Some(signature) => commit.set_signature(signature.clone()),
+                None if self.verifying.insert(commit.id().clone()) => {
+                    unchecked.push(commit.id().clone())
+                }
+                None => {}
+            }
+        }
+
+        if unchecked.is_empty() {
+            return;
+        }
+
+        let repo_path = self.repo_path.clone();
+        let event_sender = self.event_sender.clone();
+        thread::spawn(move || {
+            let signatures = unchecked
+                .into_iter()
+                .map(|id| {
+                    let signature = verify_commit(&repo_path, &id)
+                        .unwrap_or_else(|err| SignatureStatus::Error(err.to_string()));
+                    (id, signature)
+                })
+                .collect();
+
+            event_sender
+                .send(ProgramEvent::Git(GitEvent::SignaturesVerified(signatures)))
+                .expect("Send failed.");
+        });
+    }
+
+    pub fn set_signatures(&mut self, signatures: Vec<(String, SignatureStatus)>) {
+        for (id, signature) in signatures {
+            self.verifying.remove(&id);
+
+            for commit in self
+                .commits
+                .iter_mut()
+                .chain(self.filtered_commits.iter_mut())
+                .filter(|commit| commit.id() == &id)
+            {
+                commit.set_signature(signature.clone());
+            }
+            self.signatures.insert(id, signature);
+        }
+    }
+
     fn revert_commit(&self) -> Result<()> {
         if let Some(commit) = self.filtered_commits.get(self.position) {
-            revert_commit(&self.repo_path, commit)?;
+            if let Err(err) = revert_commit(&self.repo_path, commit) {
+                self.event_sender
+                    .send(ProgramEvent::Error(err))
+                    .expect("Send failed.");
+            }
         }
 
         Ok(())
@@ -148,16 +679,17 @@ impl LogComponent {
 
 impl Component for LogComponent {
     fn update(&mut self) -> Result<()> {
-        let repo = repo(&self.repo_path)?;
-        let head = repo.head()?.peel_to_commit()?.id();
-
-        self.commits = collect_commits(&self.repo_path, head)?;
-
-        if (self.commits.len() != self.filtered_commits.len()) && !self.is_searching
-            || self.input.len() <= 1
-        {
-            self.filtered_commits = self.commits.clone();
+        // Walking the history is slow on big repositories, so it's only
+        // done again when a ref moves
+        let fingerprint = refs_fingerprint(&self.repo_path)?;
+        if self.refs_fingerprint != Some(fingerprint) {
+            self.refs_fingerprint = Some(fingerprint);
+            self.reload();
+        } else if self.position + PAGE_SIZE / 2 >= self.commits.len() {
+            // Close to the end of what's loaded
+            self.load_page(PAGE_SIZE);
         }
+
         Ok(())
     }
 
@@ -165,17 +697,23 @@ impl Component for LogComponent {
         if !self.focused {
             return Ok(());
         }
+        if self.rebase_base.is_some() {
+            self.handle_rebase_event(ev);
+            return Ok(());
+        }
+        if self.is_entering_path {
+            self.handle_path_event(ev);
+            return Ok(());
+        }
         match ev.code {
             // Searching
             KeyCode::Char('j') if ev.modifiers == KeyModifiers::CONTROL => self.scroll_down(1),
             KeyCode::Char('k') if ev.modifiers == KeyModifiers::CONTROL => self.scroll_up(1),
             KeyCode::Char(c) if self.is_searching => self.push_char(c),
             KeyCode::Backspace if self.is_searching => self.pop_char(),
-            KeyCode::Esc => {
-                self.input.clear();
-                self.reset_state();
-                self.is_searching = false;
-            }
+            KeyCode::Enter if self.should_search_history() => self.run_search(),
+            KeyCode::Esc if self.is_searching => self.close_search(),
+            KeyCode::Esc if self.path_filter.is_some() => self.set_path_filter(None),
 
             // Movement
             KeyCode::Char('j') => self.scroll_down(1),
@@ -190,6 +728,10 @@ impl Component for LogComponent {
             // Program events
             KeyCode::Char('c') => self.checkout_local_branch()?,
             KeyCode::Char('r') => self.revert_commit()?,
+            KeyCode::Char('i') => self.rebase_mode(),
+            KeyCode::Char('a') => self.toggle_all_refs(),
+            KeyCode::Char('p') => self.enter_path(),
+            KeyCode::Char('f') => self.toggle_follow_renames(),
             KeyCode::Enter => self.expand_log(),
             _ => {}
         }
@@ -208,7 +750,10 @@ impl Component for LogComponent {
 
 impl ScrollableComponent for LogComponent {
     fn get_list_length(&self) -> usize {
-        self.filtered_commits.len()
+        match self.rebase_base {
+            Some(_) => self.rebase_todo.len(),
+            None => self.filtered_commits.len(),
+        }
     }
     fn get_position(&self) -> usize {
         self.position
@@ -221,6 +766,58 @@ impl ScrollableComponent for LogComponent {
     }
 }
 
+fn graph_spans(row: &[GraphCell]) -> Vec<Span<'static>> {
+    row.iter()
+        .map(|cell| {
+            let color = LANE_COLORS[cell.lane % LANE_COLORS.len()];
+            Span::styled(cell.text.clone(), Style::default().fg(color))
+        })
+        .collect()
+}
+
+/// Ref names in brackets, coloured like `git log --decorate`, followed by a
+/// space. Empty when no refs point at the commit.
+pub fn ref_spans(refs: &[RefLabel]) -> Vec<Span<'static>> {
+    if refs.is_empty() {
+        return Vec::new();
+    }
+
+    let mut spans = vec![Span::styled("(", Style::default().fg(Color::Yellow))];
+    for (index, label) in refs.iter().enumerate() {
+        if index > 0 {
+            spans.push(Span::styled(", ", Style::default().fg(Color::Yellow)));
+        }
+        let color = match label.kind {
+            RefKind::Head => Color::Cyan,
+            RefKind::Branch => Color::Green,
+            RefKind::Remote => Color::Red,
+            RefKind::Tag => Color::Yellow,
+        };
+        spans.push(Span::styled(
+            label.name.clone(),
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ));
+    }
+    spans.push(Span::styled(") ", Style::default().fg(Color::Yellow)));
+
+    spans
+}
+
+/// A one character marker for the state of a commit's signature.
+pub fn signature_span(signature: &SignatureStatus) -> Span<'static> {
+    match signature {
+        SignatureStatus::Unsigned => Span::raw(" "),
+        SignatureStatus::Unchecked => Span::styled("·", Style::default().fg(Color::DarkGray)),
+        SignatureStatus::Good(_) => Span::styled("✓", Style::default().fg(Color::Green)),
+        SignatureStatus::Bad => Span::styled("✗", Style::default().fg(Color::Red)),
+        SignatureStatus::Expired(_)
+        | SignatureStatus::ExpiredKey(_)
+        | SignatureStatus::Revoked(_) => Span::styled("!", Style::default().fg(Color::Yellow)),
+        SignatureStatus::Unknown => Span::styled("?", Style::default().fg(Color::Yellow)),
+        SignatureStatus::Error(_) => Span::styled("!", Style::default().fg(Color::Red)),
+    }
+}
+
 fn fuzzy_find(log_list: &[Commit], query: &str) -> Vec<Commit> {
     let matcher = SkimMatcherV2::default();
     log_list
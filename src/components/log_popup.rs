@@ -1,42 +1,107 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
 use crossbeam::channel::Sender;
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use tui::backend::Backend;
-use tui::layout::Rect;
-use tui::style::{Color, Style};
-use tui::text::Text;
-use tui::widgets::{Block, BorderType, Borders, Clear, Paragraph};
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans, Text};
+use tui::widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph};
 use tui::Frame;
 
+use crate::components::log::{ref_spans, signature_span};
 use crate::components::{centered_rect, Component, ComponentType};
+use crate::git::blame::BlameTarget;
+use crate::git::diff::{commit_file_diff, commit_files, ChangedFile, DiffLine};
 use crate::git::log::Commit;
+use crate::git::sign::SignatureStatus;
 use crate::ProgramEvent;
 
 pub struct LogPopup {
     commit: Commit,
     event_sender: Sender<ProgramEvent>,
+    file_state: ListState,
+    files: Vec<ChangedFile>,
+    /// Where to go back to when the popup is closed.
+    origin: ComponentType,
+    /// Which parent of a merge commit the files are compared to.
+    parent: usize,
+    /// The patch of the selected file, while it's open.
+    patch: Option<Vec<DiffLine>>,
+    patch_scroll: u16,
+    repo_path: PathBuf,
     visible: bool,
 }
 
 impl LogPopup {
-    pub fn new(event_sender: Sender<ProgramEvent>) -> Self {
+    pub fn new(repo_path: PathBuf, event_sender: Sender<ProgramEvent>) -> Self {
         Self {
             commit: Commit::new(),
             event_sender,
+            file_state: ListState::default(),
+            files: Vec::new(),
+            origin: ComponentType::LogComponent,
+            parent: 0,
+            patch: None,
+            patch_scroll: 0,
+            repo_path,
             visible: false,
         }
     }
 
     pub fn draw<B: Backend>(&mut self, f: &mut Frame<B>, rect: Rect) -> Result<()> {
+        let area = centered_rect(
+            rect.width.saturating_sub(8).min(120),
+            rect.height.saturating_sub(4),
+            rect,
+        );
+
+        let details = self.details();
+        // The files get at least half the popup
+        let details_height = (details.height() as u16 + 2).min(area.height / 2);
+
+        let container = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(details_height), Constraint::Min(3)].as_ref())
+            .split(area);
+
+        let input = Paragraph::new(details)
+            .style(Style::default().fg(Color::White))
+            .wrap(tui::widgets::Wrap { trim: false })
+            .block(
+                Block::default()
+                    .style(Style::default())
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .alignment(tui::layout::Alignment::Left);
+
+        f.render_widget(Clear, area);
+        f.render_widget(input, container[0]);
+
+        match &self.patch {
+            Some(patch) => self.draw_patch(f, container[1], patch),
+            None => self.draw_files(f, container[1]),
+        }
+        Ok(())
+    }
+
+    fn details(&self) -> Text<'static> {
         let message_body = self.commit.message_body();
-        let popup_height = message_body.len() + 8; // Normal commit info + number of lines in the commit body
-        let area = centered_rect(80, popup_height as u16, rect);
+        let refs = self.commit.refs();
 
         let mut log = Text::styled(
             format!(" commit: {}", self.commit.id()),
             Style::default().fg(Color::Yellow),
         );
 
+        if !refs.is_empty() {
+            let mut spans = vec![Span::raw(" Refs:   ")];
+            spans.extend(ref_spans(refs));
+            log.extend(Text::from(Spans::from(spans)));
+        }
+
         log.extend(Text::raw(format!(
             " Author: {} <{}>",
             self.commit.author(),
@@ -44,6 +109,22 @@ impl LogPopup {
         )));
 
         log.extend(Text::raw(format!(" Date:   {}", self.commit.time())));
+        log.extend(Text::from(Spans::from(vec![
+            Span::raw(" Signed: "),
+            signature_span(self.commit.signature()),
+            Span::raw(format!(" {}", self.commit.signature().description())),
+        ])));
+
+        let parents = self.commit.parents();
+        if parents.len() > 1 {
+            log.extend(Text::raw(format!(
+                " Diff:   against parent {} of {} ({}), p to switch",
+                self.parent + 1,
+                parents.len(),
+                &parents[self.parent][0..8]
+            )));
+        }
+
         log.extend(Text::raw(format!(
             "\n     {}\n\n",
             self.commit.message_summary()
@@ -53,42 +134,241 @@ impl LogPopup {
             log.extend(Text::raw(format!("     {}\n", line)));
         }
 
-        let input = Paragraph::new(log)
-            .style(Style::default().fg(Color::White))
-            .wrap(tui::widgets::Wrap { trim: false })
+        log
+    }
+
+    fn draw_files<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+        let items = self
+            .files
+            .iter()
+            .map(|file| {
+                let color = match file.status {
+                    'A' => Color::Green,
+                    'D' => Color::Red,
+                    'R' | 'C' => Color::Cyan,
+                    _ => Color::Yellow,
+                };
+                let path = match &file.old_path {
+                    Some(old_path) => format!("{} -> {}", old_path, file.path),
+                    None => file.path.clone(),
+                };
+
+                ListItem::new(Spans::from(vec![
+                    Span::styled(format!("{} ", file.status), Style::default().fg(color)),
+                    Span::styled(
+                        format!("{:>6}", format!("+{}", file.insertions)),
+                        Style::default().fg(Color::Green),
+                    ),
+                    Span::styled(
+                        format!("{:>6} ", format!("-{}", file.deletions)),
+                        Style::default().fg(Color::Red),
+                    ),
+                    Span::raw(path),
+                ]))
+            })
+            .collect::<Vec<_>>();
+
+        let list = List::new(items)
             .block(
                 Block::default()
-                    .style(Style::default())
+                    .title(format!(" {} file(s) changed ", self.files.len()))
                     .borders(Borders::ALL)
                     .border_type(BorderType::Rounded),
             )
-            .alignment(tui::layout::Alignment::Left);
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
 
-        f.render_widget(Clear, area);
-        f.render_widget(input, area);
-        Ok(())
+        f.render_stateful_widget(list, area, &mut self.file_state);
+    }
+
+    fn draw_patch<B: Backend>(&self, f: &mut Frame<B>, area: Rect, patch: &[DiffLine]) {
+        // Formatted the same as the diff panes
+        let lines = patch
+            .iter()
+            .flat_map(|line| {
+                let content = match line.origin() {
+                    '-' => format!("-{}", line.content()),
+                    '+' => format!("+{}", line.content()),
+                    _ => format!(" {}", line.content()),
+                };
+                // File headers come as one line with several in it
+                content
+                    .lines()
+                    .map(|content| Spans::from(Span::styled(content.to_string(), line.style())))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let title = match self.selected_file() {
+            Some(file) => format!(" {} ", file.path),
+            None => String::new(),
+        };
+        let patch = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .scroll((self.patch_scroll, 0));
+
+        f.render_widget(patch, area);
     }
 
     pub fn visible(&self) -> bool {
         self.visible
     }
 
+    /// Shows a commit, listing its files against the first parent. Showing
+    /// the same commit again keeps the file selected.
     pub fn set_commit(&mut self, commit: Commit) {
+        let same_commit = commit.id() == self.commit.id();
         self.commit = commit;
+
+        if !same_commit {
+            self.parent = 0;
+            self.load_files();
+        }
+    }
+
+    pub fn set_origin(&mut self, origin: ComponentType) {
+        self.origin = origin;
+    }
+
+    fn load_files(&mut self) {
+        self.patch = None;
+        self.files = match commit_files(&self.repo_path, self.commit.id(), self.parent) {
+            Ok(files) => files,
+            Err(err) => {
+                self.send_error(err);
+                Vec::new()
+            }
+        };
+        self.file_state.select(match self.files.is_empty() {
+            true => None,
+            false => Some(0),
+        });
+    }
+
+    fn selected_file(&self) -> Option<&ChangedFile> {
+        self.file_state
+            .selected()
+            .and_then(|index| self.files.get(index))
+    }
+
+    fn open_patch(&mut self) {
+        let file = match self.selected_file() {
+            Some(file) => file.clone(),
+            None => return,
+        };
+
+        match commit_file_diff(&self.repo_path, self.commit.id(), self.parent, &file) {
+            Ok(patch) => {
+                self.patch = Some(patch);
+                self.patch_scroll = 0;
+            }
+            Err(err) => self.send_error(err),
+        }
+    }
+
+    /// Blames the selected file as of this commit, or as it was before the
+    /// commit deleted it.
+    fn blame_file(&self) {
+        let file = match self.selected_file() {
+            Some(file) => file,
+            None => return,
+        };
+        let commit = match file.status {
+            'D' => self.commit.parents().get(self.parent),
+            _ => Some(self.commit.id()),
+        };
+
+        if let Some(commit) = commit {
+            self.event_sender
+                .send(ProgramEvent::Focus(ComponentType::BlameComponent(
+                    BlameTarget {
+                        path: file.path.clone(),
+                        commit: Some(commit.clone()),
+                    },
+                )))
+                .expect("Send failed.");
+        }
+    }
+
+    fn cycle_parent(&mut self) {
+        let parents = self.commit.parents().len();
+        if parents > 1 {
+            self.parent = (self.parent + 1) % parents;
+            self.load_files();
+        }
+    }
+
+    fn move_file(&mut self, down: bool) {
+        if let Some(selected) = self.file_state.selected() {
+            let selected = match down {
+                true => (selected + 1).min(self.files.len() - 1),
+                false => selected.saturating_sub(1),
+            };
+            self.file_state.select(Some(selected));
+        }
+    }
+
+    fn scroll_patch(&mut self, down: bool, amount: u16) {
+        let len = self.patch.as_ref().map_or(0, |patch| patch.len()) as u16;
+        self.patch_scroll = match down {
+            true => (self.patch_scroll + amount).min(len.saturating_sub(1)),
+            false => self.patch_scroll.saturating_sub(amount),
+        };
+    }
+
+    /// Picks up the signature of the commit shown once it's been verified.
+    pub fn set_signatures(&mut self, signatures: &[(String, SignatureStatus)]) {
+        if let Some((_, signature)) = signatures.iter().find(|(id, _)| id == self.commit.id()) {
+            self.commit.set_signature(signature.clone());
+        }
+    }
+
+    fn send_error(&self, err: anyhow::Error) {
+        self.event_sender
+            .send(ProgramEvent::Error(err))
+            .expect("Send failed.");
     }
 
     fn reset(&mut self) {
         self.event_sender
-            .send(ProgramEvent::Focus(ComponentType::LogComponent))
+            .send(ProgramEvent::Focus(self.origin.clone()))
             .expect("Focus event send failed.");
         self.visible = false;
+        self.patch = None;
     }
 }
 
 impl Component for LogPopup {
     fn handle_event(&mut self, ev: KeyEvent) -> Result<()> {
-        if ev.code == KeyCode::Esc {
-            self.reset()
+        if self.patch.is_some() {
+            match ev.code {
+                KeyCode::Char('j') => self.scroll_patch(true, 1),
+                KeyCode::Char('k') => self.scroll_patch(false, 1),
+                KeyCode::Char('d') if ev.modifiers == KeyModifiers::CONTROL => {
+                    self.scroll_patch(true, 10)
+                }
+                KeyCode::Char('u') if ev.modifiers == KeyModifiers::CONTROL => {
+                    self.scroll_patch(false, 10)
+                }
+                KeyCode::Esc => self.patch = None,
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        match ev.code {
+            KeyCode::Char('j') => self.move_file(true),
+            KeyCode::Char('k') => self.move_file(false),
+            KeyCode::Char('p') => self.cycle_parent(),
+            KeyCode::Char('b') => self.blame_file(),
+            KeyCode::Enter => self.open_patch(),
+            KeyCode::Esc => self.reset(),
+            _ => {}
         }
         Ok(())
     }
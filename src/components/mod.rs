@@ -2,35 +2,52 @@ use anyhow::Result;
 use crossterm::event::KeyEvent;
 use tui::layout::{Constraint, Direction, Layout, Rect};
 
+use crate::components::confirm_popup::ConfirmAction;
+use crate::git::blame::BlameTarget;
 use crate::git::diff::DiffComponentType;
 use crate::git::log::Commit;
 
+pub mod blame_popup;
 pub mod branch_popup;
 pub mod branches;
 pub mod cherry_pick_popup;
 pub mod commit_popup;
+pub mod confirm_popup;
+pub mod conflict_popup;
 pub mod diff;
 pub mod error_popup;
 pub mod files;
+pub mod hook_popup;
+pub mod identity_popup;
 pub mod log;
 pub mod log_popup;
 pub mod message_popup;
 pub mod remote_popup;
+pub mod stash;
 pub mod status;
 
 #[derive(Clone, Debug)]
 pub enum ComponentType {
+    AmendComponent,
     BranchComponent,
     BranchPopupComponent,
     CommitComponent,
+    ConflictComponent,
     ErrorComponent,
     FilesComponent,
+    HookComponent,
+    IdentityComponent,
     LogComponent,
     RemotePopupComponent,
+    StashComponent,
+    BlameComponent(BlameTarget),
     CherryPickPopup(Vec<Commit>),
+    ConfirmComponent(ConfirmAction),
     DiffComponent(DiffComponentType),
     FullLogComponent(Commit),
     MessageComponent(String),
+    /// The log of the commits changing a path.
+    PathLogComponent(String),
     None,
 }
 
@@ -49,7 +66,7 @@ pub trait ScrollableComponent {
 
     fn scroll_down(&mut self, amount: usize) {
         let len = self.get_list_length();
-        let position = max(self.get_position() + amount, len - 1);
+        let position = max(self.get_position() + amount, len.saturating_sub(1));
         self.set_position(position);
         self.set_state(position);
     }
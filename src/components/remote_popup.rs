@@ -225,6 +225,8 @@ impl Component for RemotePopupComponent {
         Ok(())
     }
 
+    // Collapsing these into match guards would let keys fall through to `push_input`.
+    #[allow(clippy::collapsible_match)]
     fn handle_event(&mut self, ev: KeyEvent) -> Result<()> {
         match ev.code {
             KeyCode::Char('j') => {
@@ -0,0 +1,189 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use crossbeam::channel::Sender;
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, BorderType, Borders, List as TuiList, ListItem, ListState};
+use tui::Frame;
+
+use crate::app::ProgramEvent;
+use crate::component_style::ComponentTheme;
+use crate::components::confirm_popup::ConfirmAction;
+use crate::components::{Component, ComponentType, ScrollableComponent};
+use crate::git::diff::DiffComponentType;
+use crate::git::stash::{get_stashes, stash_apply, stash_pop, stash_save, stash_staged, Stash};
+
+pub struct StashComponent {
+    event_sender: Sender<ProgramEvent>,
+    focused: bool,
+    position: usize,
+    repo_path: PathBuf,
+    stashes: Vec<Stash>,
+    state: ListState,
+    style: ComponentTheme,
+}
+
+impl StashComponent {
+    pub fn new(repo_path: PathBuf, event_sender: Sender<ProgramEvent>) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+
+        Self {
+            event_sender,
+            focused: false,
+            position: 0,
+            repo_path,
+            stashes: Vec::new(),
+            state,
+            style: ComponentTheme::default(),
+        }
+    }
+
+    pub fn draw<B: Backend>(&mut self, f: &mut Frame<B>, rect: Rect) -> Result<()> {
+        let list_items: Vec<ListItem> = self
+            .stashes
+            .iter()
+            .map(|stash| {
+                let time = String::from(*stash.time.time_since_commit());
+
+                ListItem::new(Spans::from(vec![
+                    Span::styled(
+                        format!("stash@{{{}}}", stash.index),
+                        Style::default().fg(Color::Green),
+                    ),
+                    Span::raw(" "),
+                    Span::raw(stash.message.clone()),
+                    Span::raw(" "),
+                    Span::styled(format!("({})", time), Style::default().fg(Color::Yellow)),
+                ]))
+            })
+            .collect();
+
+        let list = TuiList::new(list_items)
+            .block(
+                Block::default()
+                    .title(" Stash ")
+                    .style(self.style.style())
+                    .borders(Borders::ALL)
+                    .border_style(self.style.border_style())
+                    .border_type(BorderType::Rounded),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
+
+        f.render_stateful_widget(list, rect, &mut self.state);
+
+        Ok(())
+    }
+
+    fn save(&self, include_untracked: bool) {
+        self.send_result(stash_save(&self.repo_path, include_untracked));
+    }
+
+    fn save_staged(&self) {
+        self.send_result(stash_staged(&self.repo_path));
+    }
+
+    fn apply(&self) {
+        if let Some(stash) = self.stashes.get(self.position) {
+            self.send_result(stash_apply(&self.repo_path, stash.index));
+        }
+    }
+
+    fn pop(&self) {
+        if let Some(stash) = self.stashes.get(self.position) {
+            self.send_result(stash_pop(&self.repo_path, stash.index));
+        }
+    }
+
+    fn drop_stash(&self) {
+        if let Some(stash) = self.stashes.get(self.position) {
+            self.event_sender
+                .send(ProgramEvent::Focus(ComponentType::ConfirmComponent(
+                    ConfirmAction::DropStash(stash.index),
+                )))
+                .expect("Send failed.");
+        }
+    }
+
+    fn show_diff(&self) {
+        if let Some(stash) = self.stashes.get(self.position) {
+            self.event_sender
+                .send(ProgramEvent::Focus(ComponentType::DiffComponent(
+                    DiffComponentType::Stash(stash.id.clone()),
+                )))
+                .expect("Send failed.");
+        }
+    }
+
+    fn send_result(&self, result: Result<()>) {
+        if let Err(err) = result {
+            self.event_sender
+                .send(ProgramEvent::Error(err))
+                .expect("Send failed.");
+        }
+    }
+}
+
+impl Component for StashComponent {
+    fn update(&mut self) -> Result<()> {
+        self.stashes = get_stashes(&self.repo_path)?;
+
+        if self.position >= self.stashes.len() && !self.stashes.is_empty() {
+            let position = self.stashes.len() - 1;
+            self.set_position(position);
+            self.set_state(position);
+        }
+
+        Ok(())
+    }
+
+    fn handle_event(&mut self, ev: KeyEvent) -> Result<()> {
+        if !self.focused {
+            return Ok(());
+        }
+
+        match ev.code {
+            KeyCode::Char('j') => self.scroll_down(1),
+            KeyCode::Char('k') => self.scroll_up(1),
+            KeyCode::Char('s') => self.save(false),
+            KeyCode::Char('S') => self.save_staged(),
+            KeyCode::Char('U') => self.save(true),
+            KeyCode::Char('a') => self.apply(),
+            KeyCode::Char('p') => self.pop(),
+            KeyCode::Char('d') => self.drop_stash(),
+            KeyCode::Enter => self.show_diff(),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn focus(&mut self, focus: bool) {
+        if focus {
+            self.style = ComponentTheme::focused();
+        } else {
+            self.style = ComponentTheme::default();
+        }
+        self.focused = focus;
+    }
+}
+
+impl ScrollableComponent for StashComponent {
+    fn get_list_length(&self) -> usize {
+        self.stashes.len()
+    }
+    fn get_position(&self) -> usize {
+        self.position
+    }
+    fn set_position(&mut self, position: usize) {
+        self.position = position;
+    }
+    fn set_state(&mut self, position: usize) {
+        self.state.select(Some(position));
+    }
+}
@@ -4,17 +4,19 @@ use anyhow::Result;
 use crossterm::event::KeyEvent;
 use tui::backend::Backend;
 use tui::layout::{Constraint, Direction, Layout, Rect};
-use tui::style::{Color, Style};
+use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::widgets::{Block, BorderType, Borders, Paragraph};
 use tui::Frame;
 
 use crate::components::Component;
 use crate::git::diff::{get_diff_stats, DiffWindow};
+use crate::git::state::get_state_label;
 
 #[allow(unused)]
 pub struct StatusComponent {
     repo_path: PathBuf,
+    state: Option<&'static str>,
     status: DiffWindow,
 }
 
@@ -22,6 +24,7 @@ impl StatusComponent {
     pub fn new(repo_path: PathBuf) -> Self {
         Self {
             repo_path,
+            state: None,
             status: DiffWindow::default(),
         }
     }
@@ -58,10 +61,27 @@ impl StatusComponent {
         let diff_status = Paragraph::new(text).style(Style::default());
         f.render_widget(diff_status, container[1]);
 
-        let text = Spans::from(vec![
+        let mut text = vec![
             Span::raw(" On Branch: "),
             Span::styled(&self.status.branch, Style::default().fg(Color::Yellow)),
-        ]);
+        ];
+
+        // Half-finished operations are easy to forget about
+        if let Some(state) = self.state {
+            text.push(Span::raw(" "));
+            text.push(Span::styled(
+                format!(" {} ", state),
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Red)
+                    .add_modifier(Modifier::BOLD),
+            ));
+            text.push(Span::styled(
+                " r continue, x abort",
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        let text = Spans::from(text);
         let branch_status = Paragraph::new(text).style(Style::default());
         f.render_widget(branch_status, container[0]);
         Ok(())
@@ -71,6 +91,7 @@ impl StatusComponent {
 impl Component for StatusComponent {
     fn update(&mut self) -> Result<()> {
         self.status = get_diff_stats(&self.repo_path)?;
+        self.state = get_state_label(&self.repo_path)?;
         Ok(())
     }
 
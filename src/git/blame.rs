@@ -0,0 +1,202 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use git2::{BlameOptions, DiffOptions, Oid, Patch};
+
+use crate::git::repo;
+use crate::git::time::CommitDate;
+
+/// A file to blame, as it is in a commit or in the working tree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlameTarget {
+    pub path: String,
+    /// The commit to blame the file at, the working tree when `None`.
+    pub commit: Option<String>,
+}
+
+/// A line of a file and the commit that last changed it.
+#[derive(Clone, Debug)]
+pub struct BlameLine {
+    /// `None` when the line was changed in the working tree.
+    pub commit: Option<String>,
+    pub author: String,
+    /// The file's path in that commit, it may have been renamed since.
+    pub path: String,
+    pub time: CommitDate,
+    pub content: String,
+}
+
+pub fn blame_file(repo_path: &Path, target: &BlameTarget) -> Result<Vec<BlameLine>> {
+    let repo = repo(repo_path)?;
+    let path = Path::new(&target.path);
+
+    let commit = match &target.commit {
+        Some(id) => repo.find_commit(Oid::from_str(id)?)?,
+        None => repo.head()?.peel_to_commit()?,
+    };
+    let committed = match commit.tree()?.get_path(path) {
+        Ok(entry) => Some(entry.to_object(&repo)?.peel_to_blob()?.content().to_vec()),
+        Err(_) if target.commit.is_none() => None,
+        Err(err) => return Err(err.into()),
+    };
+
+    let content = match (&target.commit, &committed) {
+        (Some(_), Some(committed)) => committed.clone(),
+        _ => {
+            let workdir = repo
+                .workdir()
+                .ok_or_else(|| anyhow!("A bare repository has no working tree to blame"))?;
+            std::fs::read(workdir.join(path))
+                .map_err(|err| anyhow!("Can't read {}: {}", target.path, err))?
+        }
+    };
+    if content.contains(&0) {
+        return Err(anyhow!("{} is a binary file", target.path));
+    }
+
+    let content = String::from_utf8_lossy(&content);
+    let lines = content.lines().collect::<Vec<_>>();
+
+    // The line of the committed file each line comes from
+    let committed_lines = match (&target.commit, &committed) {
+        (None, Some(committed)) => working_tree_lines(committed, content.as_bytes(), lines.len())?,
+        (None, None) => vec![None; lines.len()],
+        (Some(_), _) => (1..=lines.len()).map(Some).collect(),
+    };
+
+    let mut options = BlameOptions::new();
+    options.newest_commit(commit.id());
+    let blame = match committed {
+        Some(_) => Some(repo.blame_file(path, Some(&mut options))?),
+        None => None,
+    };
+
+    let mut blamed = Vec::with_capacity(lines.len());
+    for (content, line) in lines.iter().zip(committed_lines) {
+        let hunk = blame
+            .as_ref()
+            .zip(line)
+            .and_then(|(blame, line)| blame.get_line(line));
+
+        blamed.push(match hunk {
+            Some(hunk) if !hunk.final_commit_id().is_zero() => {
+                let signature = hunk.final_signature();
+                BlameLine {
+                    commit: Some(hunk.final_commit_id().to_string()),
+                    author: signature.name().unwrap_or("").to_string(),
+                    path: hunk.path().map_or(target.path.clone(), |path| {
+                        path.to_string_lossy().to_string()
+                    }),
+                    time: CommitDate::new(signature.when()),
+                    content: content.to_string(),
+                }
+            }
+            _ => BlameLine {
+                commit: None,
+                author: "Not committed yet".to_string(),
+                path: target.path.clone(),
+                time: CommitDate::new(git2::Time::new(chrono::Utc::now().timestamp(), 0)),
+                content: content.to_string(),
+            },
+        });
+    }
+
+    Ok(blamed)
+}
+
+/// Where to blame a line again to see it before its commit: the commit's
+/// first parent, under the file's name there.
+pub fn blame_parent(repo_path: &Path, line: &BlameLine) -> Result<BlameTarget> {
+    let id = line
+        .commit
+        .as_ref()
+        .ok_or_else(|| anyhow!("The line isn't committed yet"))?;
+    let repo = repo(repo_path)?;
+    let commit = repo.find_commit(Oid::from_str(id)?)?;
+
+    let parent = commit.parent(0).map_err(|_| {
+        anyhow!(
+            "{} is the first commit, there's nothing before it",
+            &id[0..8]
+        )
+    })?;
+    if parent.tree()?.get_path(Path::new(&line.path)).is_err() {
+        return Err(anyhow!("{} was added in {}", line.path, &id[0..8]));
+    }
+
+    Ok(BlameTarget {
+        path: line.path.clone(),
+        commit: Some(parent.id().to_string()),
+    })
+}
+
+/// Maps each line of the working tree copy of a file to its line in the
+/// committed copy, `None` for lines changed since. Lines count from 1.
+fn working_tree_lines(
+    committed: &[u8],
+    working: &[u8],
+    count: usize,
+) -> Result<Vec<Option<usize>>> {
+    let mut options = DiffOptions::new();
+    options.context_lines(0);
+    let patch = Patch::from_buffers(committed, None, working, None, Some(&mut options))?;
+
+    let mut lines = Vec::with_capacity(count);
+    // How far the committed line is from the working tree line
+    let mut offset = 0isize;
+
+    for index in 0..patch.num_hunks() {
+        let (hunk, _) = patch.hunk(index)?;
+        let (old_start, old_lines) = (hunk.old_start() as usize, hunk.old_lines() as usize);
+        let (new_start, new_lines) = (hunk.new_start() as usize, hunk.new_lines() as usize);
+
+        // A hunk with no lines on a side starts after the line given
+        let changed = if new_lines == 0 {
+            new_start + 1
+        } else {
+            new_start
+        };
+        while lines.len() + 1 < changed {
+            lines.push(Some((lines.len() as isize + 1 + offset) as usize));
+        }
+        lines.extend((0..new_lines).map(|_| None));
+
+        let old_end = if old_lines == 0 {
+            old_start + 1
+        } else {
+            old_start + old_lines
+        };
+        offset = old_end as isize - (changed + new_lines) as isize;
+    }
+    while lines.len() < count {
+        lines.push(Some((lines.len() as isize + 1 + offset) as usize));
+    }
+
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unchanged_lines_keep_their_place() {
+        let lines = working_tree_lines(b"a\nb\nc\n", b"a\nb\nc\n", 3).unwrap();
+
+        assert_eq!(lines, vec![Some(1), Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn added_and_changed_lines_are_uncommitted() {
+        let lines = working_tree_lines(b"a\nb\nc\n", b"new\na\nB\nc\nend\n", 5).unwrap();
+
+        assert_eq!(lines, vec![None, Some(1), None, Some(3), None]);
+    }
+
+    #[test]
+    fn deleted_lines_shift_the_rest() {
+        let lines = working_tree_lines(b"a\nb\nc\nd\n", b"a\nd\n", 2).unwrap();
+
+        assert_eq!(lines, vec![Some(1), Some(4)]);
+    }
+}
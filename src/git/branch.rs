@@ -1,11 +1,13 @@
 use std::path::Path;
 
 use anyhow::Result;
-use git2::{BranchType, Repository};
+use git2::build::CheckoutBuilder;
+use git2::{BranchType, CheckoutNotificationType, ErrorCode, Repository};
 
 use crate::git::diff::head;
 use crate::git::log::Commit;
 use crate::git::repo;
+use crate::git::stash::{stash_pop, stash_save};
 
 #[derive(Clone, Debug)]
 pub struct Branch {
@@ -18,17 +20,76 @@ pub fn checkout_local_branch(repo_path: &Path, branch_name: &str) -> Result<()>
     let repo = repo(repo_path)?;
 
     // Need to change the files in the working directory as well as set the HEAD
-    let (object, reference) = repo.revparse_ext(branch_name).expect("Object not found");
+    let (object, reference) = repo.revparse_ext(branch_name)?;
 
     repo.checkout_tree(&object, None)?;
 
     match reference {
         // gref is an actual reference like branches or tags
-        Some(gref) => repo.set_head(gref.name().unwrap()),
+        Some(gref) => match gref.name() {
+            Some(name) => repo.set_head(name)?,
+            None => return Err(anyhow::Error::msg("Branch name is not valid UTF-8")),
+        },
         // this is a commit, not a reference
-        None => repo.set_head_detached(object.id()),
+        None => repo.set_head_detached(object.id())?,
+    }
+
+    Ok(())
+}
+
+pub fn checkout_branch(repo_path: &Path, branch_name: &str, branch_type: BranchType) -> Result<()> {
+    match branch_type {
+        BranchType::Local => checkout_local_branch(repo_path, branch_name),
+        BranchType::Remote => checkout_remote_branch(repo_path, branch_name),
+    }
+}
+
+/// Files with local changes that checking out `branch_name` would overwrite.
+pub fn checkout_conflicts(repo_path: &Path, branch_name: &str) -> Result<Vec<String>> {
+    let repo = repo(repo_path)?;
+    let (object, _reference) = repo.revparse_ext(branch_name)?;
+    let mut conflicts = Vec::new();
+
+    let mut checkout = CheckoutBuilder::new();
+    checkout
+        .dry_run()
+        .notify_on(CheckoutNotificationType::CONFLICT)
+        .notify(|_, path, _, _, _| {
+            if let Some(path) = path {
+                conflicts.push(path.to_string_lossy().to_string());
+            }
+            true
+        });
+
+    match repo.checkout_tree(&object, Some(&mut checkout)) {
+        Err(err) if err.code() != ErrorCode::Conflict => return Err(anyhow::Error::from(err)),
+        _ => {}
+    }
+    drop(checkout);
+
+    Ok(conflicts)
+}
+
+/// Stashes local changes, checks out the branch and reapplies them.
+pub fn checkout_with_autostash(
+    repo_path: &Path,
+    branch_name: &str,
+    branch_type: BranchType,
+) -> Result<()> {
+    stash_save(repo_path, true)?;
+
+    if let Err(err) = checkout_branch(repo_path, branch_name, branch_type) {
+        stash_pop(repo_path, 0)?;
+        return Err(err);
+    }
+
+    if let Err(err) = stash_pop(repo_path, 0) {
+        return Err(anyhow::anyhow!(
+            "Checked out {}, but reapplying local changes failed: {}",
+            branch_name,
+            err
+        ));
     }
-    .expect("Failed to set HEAD");
 
     Ok(())
 }
@@ -43,22 +104,16 @@ pub fn checkout_remote_branch(repo_path: &Path, remote_branch_name: &str) -> Res
         return Err(anyhow::Error::msg("Local branch already exists"));
     }
 
-    let (object, _reference) = repo
-        .revparse_ext(remote_branch_name)
-        .expect("Object not found");
-    let commit = object.as_commit().unwrap();
-
-    repo.branch(&name, commit, false)?;
+    let commit = repo.revparse_single(remote_branch_name)?.peel_to_commit()?;
 
-    let (object, reference) = repo.revparse_ext(&name).expect("Object not found");
+    // Check out before creating the branch so a conflict doesn't leave it behind
+    repo.checkout_tree(commit.as_object(), None)?;
+    let branch = repo.branch(&name, &commit, false)?;
 
-    repo.checkout_tree(&object, None)?;
-
-    match reference {
-        Some(gref) => repo.set_head(gref.name().unwrap()),
-        None => repo.set_head_detached(object.id()),
+    match branch.get().name() {
+        Some(refname) => repo.set_head(refname)?,
+        None => return Err(anyhow::Error::msg("Branch name is not valid UTF-8")),
     }
-    .expect("Failed to set HEAD");
 
     set_upstream_branch(repo_path, remote_name, &name)?;
 
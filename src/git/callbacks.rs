@@ -49,8 +49,8 @@ pub fn create_remote_callbacks(
 
     callbacks.push_update_reference(|_remote, _status| {
         // TODO
-        if _status.is_some() {
-            panic!("oh no {}", _status.unwrap());
+        if let Some(status) = _status {
+            panic!("oh no {}", status);
         }
         Ok(())
     });
@@ -1,14 +1,25 @@
 use std::path::Path;
 
 use anyhow::Result;
-use git2::{AnnotatedCommit, Config, Oid, Signature};
+use crossbeam::channel::Sender;
+use git2::build::CheckoutBuilder;
+use git2::{
+    AnnotatedCommit, CherrypickOptions, Oid, Repository, RepositoryState, RevertOptions, Signature,
+    Tree,
+};
 
+use crate::app::ProgramEvent;
+use crate::git::hooks::run_hook;
+use crate::git::identity::{author_signature, signature};
 use crate::git::log::Commit;
 use crate::git::repo;
+use crate::git::sign::sign_commit;
+use crate::git::state::ensure_clean_state;
+use crate::git::status::get_conflicted_paths;
 
 pub fn create_initial_commit(repo_path: &Path) -> Result<()> {
     let repo = repo(repo_path)?;
-    let signature = signature()?;
+    let signature = signature(repo_path)?;
 
     let mut index = repo.index()?;
     let id = index.write_tree()?;
@@ -25,14 +36,30 @@ pub fn create_initial_commit(repo_path: &Path) -> Result<()> {
     Ok(())
 }
 
-pub fn commit(repo_path: &Path, message: &str, merge_commit: Option<git2::Oid>) -> Result<()> {
+pub fn commit(
+    repo_path: &Path,
+    message: &str,
+    merge_commit: Option<git2::Oid>,
+    reflog: Option<&str>,
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<()> {
+    let message = run_commit_hooks(repo_path, message, event_sender)?;
     let repo = repo(repo_path)?;
-    let signature = signature()?;
+    let author = author_signature(repo_path)?;
+    let committer = signature(repo_path)?;
 
     let mut index = repo.index()?;
     let id = index.write_tree()?;
     let tree = repo.find_tree(id)?;
 
+    // Committing during a merge finishes it, the same as `git commit`
+    let merge_commit = match merge_commit {
+        None if repo.state() == RepositoryState::Merge => {
+            repo.find_reference("MERGE_HEAD")?.target()
+        }
+        merge_commit => merge_commit,
+    };
+
     let mut merge_commit_id = match merge_commit {
         Some(id) => vec![repo.find_commit(id)?],
         None => Vec::new(),
@@ -42,12 +69,20 @@ pub fn commit(repo_path: &Path, message: &str, merge_commit: Option<git2::Oid>)
         let mut commit = vec![repo.find_commit(head)?];
         commit.append(&mut merge_commit_id);
 
+        // What `git commit` logs, unless finishing something else
+        let reflog = match reflog {
+            Some(reflog) => reflog,
+            None if commit.len() > 1 => "commit (merge)",
+            None => "commit",
+        };
+
         let parents = commit.iter().collect::<Vec<_>>();
-        repo.commit(
-            Some("HEAD"),
-            &signature,
-            &signature,
-            message,
+        commit_to_head(
+            &repo,
+            reflog,
+            &author,
+            &committer,
+            &message,
             &tree,
             parents.as_slice(),
         )?;
@@ -55,10 +90,209 @@ pub fn commit(repo_path: &Path, message: &str, merge_commit: Option<git2::Oid>)
         return Err(anyhow::Error::msg("Make me a meaninful error message"));
     }
 
+    if matches!(
+        repo.state(),
+        RepositoryState::Merge
+            | RepositoryState::Revert
+            | RepositoryState::RevertSequence
+            | RepositoryState::CherryPick
+            | RepositoryState::CherryPickSequence
+    ) {
+        repo.cleanup_state()?;
+    }
+
+    run_post_commit_hook(repo_path, event_sender);
+
     Ok(())
 }
 
-pub fn merge_commit(repo_path: &Path, annotated_commit: AnnotatedCommit) -> Result<()> {
+/// Creates a commit and moves HEAD to it, signing it first when
+/// `commit.gpgsign` is set. HEAD is moved by hand so this also works when
+/// amending, where HEAD isn't the first parent. The reflog entry starts
+/// with `reflog`, such as `commit (amend)`, the same as git's.
+pub fn commit_to_head(
+    repo: &Repository,
+    reflog: &str,
+    author: &Signature,
+    committer: &Signature,
+    message: &str,
+    tree: &Tree,
+    parents: &[&git2::Commit],
+) -> Result<Oid> {
+    let buffer = repo.commit_create_buffer(author, committer, message, tree, parents)?;
+    let buffer = buffer
+        .as_str()
+        .ok_or_else(|| anyhow::Error::msg("The commit isn't valid UTF-8"))?;
+
+    let id = match sign_commit(repo, buffer, committer)? {
+        Some(signature) => repo.commit_signed(buffer, &signature, None)?,
+        None => repo.commit(None, author, committer, message, tree, parents)?,
+    };
+    let log_message = format!("{}: {}", reflog, message.lines().next().unwrap_or(""));
+
+    // Moving the branch logs it in HEAD's reflog too
+    let head = repo.find_reference("HEAD")?;
+    let target = head.symbolic_target().unwrap_or("HEAD");
+    repo.reference(target, id, true, &log_message)?;
+
+    Ok(id)
+}
+
+/// Runs the `pre-commit` and `commit-msg` hooks, which libgit2 doesn't, and
+/// returns the message as `commit-msg` left it. Either hook failing aborts
+/// the commit.
+pub fn run_commit_hooks(
+    repo_path: &Path,
+    message: &str,
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<String> {
+    run_hook(repo_path, "pre-commit", &[], event_sender)?;
+
+    // Hooks expect the message to end in a newline, as git writes it
+    let message_file = repo(repo_path)?.path().join("COMMIT_EDITMSG");
+    std::fs::write(&message_file, format!("{}\n", message.trim_end()))?;
+
+    let path = message_file.to_str().unwrap_or("");
+    if !run_hook(repo_path, "commit-msg", &[path], event_sender)? {
+        return Ok(message.to_string());
+    }
+
+    Ok(std::fs::read_to_string(&message_file)?)
+}
+
+/// Runs the `post-commit` hook. Like git, a failing hook doesn't undo the
+/// commit, the failure is only reported.
+pub fn run_post_commit_hook(repo_path: &Path, event_sender: &Sender<ProgramEvent>) {
+    if let Err(err) = run_hook(repo_path, "post-commit", &[], event_sender) {
+        event_sender
+            .send(ProgramEvent::Error(err))
+            .expect("Send failed.");
+    }
+}
+
+/// Replaces HEAD with a commit of the current index, keeping its author and
+/// parents.
+pub fn amend(repo_path: &Path, message: &str, event_sender: &Sender<ProgramEvent>) -> Result<()> {
+    if repo(repo_path)?.state() == RepositoryState::Merge {
+        return Err(anyhow::Error::msg(
+            "Finish or abort the merge before amending",
+        ));
+    }
+
+    let message = run_commit_hooks(repo_path, message, event_sender)?;
+    let repo = repo(repo_path)?;
+
+    let mut index = repo.index()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let parents = head.parents().collect::<Vec<_>>();
+
+    commit_to_head(
+        &repo,
+        "commit (amend)",
+        &head.author(),
+        &signature(repo_path)?,
+        &message,
+        &tree,
+        &parents.iter().collect::<Vec<_>>(),
+    )?;
+
+    run_post_commit_hook(repo_path, event_sender);
+
+    Ok(())
+}
+
+/// The message to start editing a commit with, the same one `git commit`
+/// would open the editor with: HEAD's message when amending, otherwise the
+/// merge message or `commit.template`. It's passed through the
+/// `prepare-commit-msg` hook.
+pub fn prepare_message(
+    repo_path: &Path,
+    amend: bool,
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<String> {
+    let repo = repo(repo_path)?;
+
+    let (message, source) = if amend {
+        (head_message(repo_path)?, vec!["commit", "HEAD"])
+    } else if let Ok(message) = repo.message() {
+        (message, vec!["merge"])
+    } else if let Some(template) = commit_template(&repo)? {
+        (template, vec!["template"])
+    } else {
+        (String::new(), Vec::new())
+    };
+
+    let message_file = repo.path().join("COMMIT_EDITMSG");
+    std::fs::write(&message_file, &message)?;
+
+    let mut args = vec![message_file.to_str().unwrap_or("")];
+    args.extend(source);
+    if !run_hook(repo_path, "prepare-commit-msg", &args, event_sender)? {
+        return Ok(message);
+    }
+
+    Ok(std::fs::read_to_string(&message_file)?)
+}
+
+fn commit_template(repo: &Repository) -> Result<Option<String>> {
+    let path = match repo.config()?.get_path("commit.template") {
+        Ok(path) => path,
+        Err(_) => return Ok(None),
+    };
+
+    let path = match repo.workdir() {
+        Some(workdir) if path.is_relative() => workdir.join(path),
+        _ => path,
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(template) => Ok(Some(template)),
+        Err(err) => Err(anyhow::anyhow!(
+            "Couldn't read the commit template {}: {}",
+            path.display(),
+            err
+        )),
+    }
+}
+
+pub fn head_message(repo_path: &Path) -> Result<String> {
+    let repo = repo(repo_path)?;
+    let head = repo.head()?.peel_to_commit()?;
+    Ok(head.message().unwrap_or("").to_string())
+}
+
+/// The upstream branch HEAD has already been pushed to, if any.
+pub fn head_pushed_to(repo_path: &Path) -> Result<Option<String>> {
+    let repo = repo(repo_path)?;
+    let head = repo.head()?;
+
+    if !head.is_branch() {
+        return Ok(None);
+    }
+
+    let branch = git2::Branch::wrap(head);
+    let upstream = match branch.upstream() {
+        Ok(upstream) => upstream,
+        Err(_) => return Ok(None),
+    };
+
+    let head_id = branch.get().peel_to_commit()?.id();
+    let upstream_id = upstream.get().peel_to_commit()?.id();
+
+    if head_id == upstream_id || repo.graph_descendant_of(upstream_id, head_id)? {
+        Ok(upstream.name()?.map(|name| name.to_string()))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn merge_commit(
+    repo_path: &Path,
+    annotated_commit: AnnotatedCommit,
+    reflog: &str,
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<()> {
     // I'm not certain the 'merge' will fail if there is a conflict since
     // we have to manually commit anyways. I don't want to test it so I'll assume
     // I have to check it here before attempting to commit :D
@@ -71,58 +305,126 @@ pub fn merge_commit(repo_path: &Path, annotated_commit: AnnotatedCommit) -> Resu
         ));
     } else {
         let msg = repo.message()?;
-        commit(repo_path, &msg, Some(annotated_commit.id()))?;
+        commit(
+            repo_path,
+            &msg,
+            Some(annotated_commit.id()),
+            Some(reflog),
+            event_sender,
+        )?;
     }
 
     Ok(())
 }
 
+/// Merges a commit into HEAD and commits the result. When it conflicts the
+/// repository is left in the MERGING state with the conflicts checked out,
+/// the same as `git merge`. `reflog` starts the merge commit's reflog entry.
+pub fn merge_into_head(
+    repo_path: &Path,
+    repo: &Repository,
+    annotated_commit: AnnotatedCommit,
+    reflog: &str,
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<()> {
+    ensure_clean_state(repo_path)?;
+    repo.merge(&[&annotated_commit], None, Some(&mut conflict_checkout()))?;
+
+    let conflicts = get_conflicted_paths(repo_path)?;
+    if !conflicts.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Merge conflicts in {}. Resolve them from the conflicts view (m in Files).",
+            conflicts.join(", ")
+        ));
+    }
+
+    merge_commit(repo_path, annotated_commit, reflog, event_sender)?;
+    repo.cleanup_state()?;
+
+    Ok(())
+}
+
 pub fn revert_commit(repo_path: &Path, commit: &Commit) -> Result<()> {
     let repo = repo(repo_path)?;
     let oid = Oid::from_str(commit.id())?;
     let commit = repo.find_commit(oid)?;
 
-    repo.revert(&commit, None)?;
+    ensure_clean_state(repo_path)?;
+    let mut opts = RevertOptions::new();
+    opts.checkout_builder(conflict_checkout());
+    repo.revert(&commit, Some(&mut opts))?;
+
+    let conflicts = get_conflicted_paths(repo_path)?;
+    if !conflicts.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Revert conflicts in {}. Resolve them from the conflicts view (m in Files).",
+            conflicts.join(", ")
+        ));
+    }
 
     Ok(())
 }
 
-pub fn cherry_pick(repo_path: &Path, oids: &Vec<String>) -> Result<()> {
+/// Picks each commit onto HEAD in turn. A conflicting pick stops there and
+/// leaves the repository in the CHERRYPICKING state to be resolved.
+pub fn cherry_pick(
+    repo_path: &Path,
+    oids: &[String],
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<()> {
+    ensure_clean_state(repo_path)?;
     let repo = repo(repo_path)?;
-    let head = repo.head()?.peel_to_commit()?;
-    let opts = git2::MergeOptions::new();
-    let commiter = signature()?;
+    let commiter = signature(repo_path)?;
 
-    // Don't ask
-    for oid in oids {
+    for (picked, oid) in oids.iter().enumerate() {
         let commit = repo.find_commit(Oid::from_str(oid)?)?;
-        let mut index = repo.cherrypick_commit(&commit, &head, 0, Some(&opts))?;
-        let tree_oid = index.write_tree_to(&repo)?;
-        let tree = repo.find_tree(tree_oid)?;
-        let author = commit.author();
-        let message = commit.message().unwrap_or("");
 
-        repo.commit(Some("HEAD"), &author, &commiter, message, &tree, &[&head])?;
-    }
+        let mut opts = CherrypickOptions::new();
+        opts.checkout_builder(conflict_checkout());
+        repo.cherrypick(&commit, Some(&mut opts))?;
 
-    Ok(())
-}
+        let conflicts = get_conflicted_paths(repo_path)?;
+        if !conflicts.is_empty() {
+            let skipped = match oids.len() - picked - 1 {
+                0 => String::new(),
+                n => format!(" The {} commit(s) after it were not picked.", n),
+            };
+            return Err(anyhow::anyhow!(
+                "Cherry-picking {} conflicted in {}. Resolve them from the conflicts view (m in Files).{}",
+                &oid[0..7.min(oid.len())],
+                conflicts.join(", "),
+                skipped
+            ));
+        }
 
-fn signature() -> Result<Signature<'static>> {
-    // Is there a better way to do this?
-    let config = Config::open_default()?;
+        let message = run_commit_hooks(repo_path, commit.message().unwrap_or(""), event_sender)?;
 
-    if let Some(name) = config.get_entry("user.name")?.value() {
-        if let Some(email) = config.get_entry("user.email")?.value() {
-            let signature = Signature::now(name, email)?;
-            return Ok(signature);
-        }
+        // The pre-commit hook may have staged changes of its own
+        let mut index = repo.index()?;
+        index.read(false)?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let head = repo.head()?.peel_to_commit()?;
 
-        let signature = Signature::now(name, "")?;
-        return Ok(signature);
+        commit_to_head(
+            &repo,
+            "cherry-pick",
+            &commit.author(),
+            &commiter,
+            &message,
+            &tree,
+            &[&head],
+        )?;
+        repo.cleanup_state()?;
+        run_post_commit_hook(repo_path, event_sender);
     }
 
-    let signature = Signature::now("", "")?;
+    Ok(())
+}
 
-    Ok(signature)
+/// Checkout options that write conflicts to the working tree with the base
+/// version included, so the conflicts view can show all three sides.
+pub fn conflict_checkout() -> CheckoutBuilder<'static> {
+    let mut checkout = CheckoutBuilder::new();
+    checkout.allow_conflicts(true).conflict_style_diff3(true);
+    checkout
 }
@@ -0,0 +1,269 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::git::repo;
+
+/// One `<<<<<<<` ... `>>>>>>>` region of a conflicted file. `start` and `end`
+/// are the line indices of the opening and closing markers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConflictHunk {
+    pub start: usize,
+    pub end: usize,
+    pub ours: Vec<String>,
+    pub base: Vec<String>,
+    pub theirs: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resolution {
+    Ours,
+    Theirs,
+    Both,
+}
+
+enum Section {
+    Ours,
+    Base,
+    Theirs,
+}
+
+pub fn parse_conflicts(content: &str) -> Vec<ConflictHunk> {
+    let mut hunks = Vec::new();
+    let mut current: Option<(ConflictHunk, Section)> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        current = match current {
+            None if line.starts_with("<<<<<<<") => Some((
+                ConflictHunk {
+                    start: index,
+                    end: index,
+                    ours: Vec::new(),
+                    base: Vec::new(),
+                    theirs: Vec::new(),
+                },
+                Section::Ours,
+            )),
+            None => None,
+            Some((hunk, Section::Ours)) if line.starts_with("|||||||") => {
+                Some((hunk, Section::Base))
+            }
+            Some((hunk, Section::Ours | Section::Base)) if line.starts_with("=======") => {
+                Some((hunk, Section::Theirs))
+            }
+            Some((mut hunk, Section::Theirs)) if line.starts_with(">>>>>>>") => {
+                hunk.end = index;
+                hunks.push(hunk);
+                None
+            }
+            Some((mut hunk, section)) => {
+                match section {
+                    Section::Ours => hunk.ours.push(line.to_string()),
+                    Section::Base => hunk.base.push(line.to_string()),
+                    Section::Theirs => hunk.theirs.push(line.to_string()),
+                }
+                Some((hunk, section))
+            }
+        };
+    }
+
+    hunks
+}
+
+/// Replaces a conflict region with the chosen side(s). Every line keeps
+/// its own terminator, so CRLF files and a missing final newline survive.
+pub fn resolve_hunk(content: &str, hunk: &ConflictHunk, resolution: Resolution) -> String {
+    let lines = content.split_inclusive('\n').collect::<Vec<_>>();
+
+    // The sides are the lines after the opening and before the closing marker
+    let ours = &lines[hunk.start + 1..hunk.start + 1 + hunk.ours.len()];
+    let theirs = &lines[hunk.end - hunk.theirs.len()..hunk.end];
+    let chosen = match resolution {
+        Resolution::Ours => ours.to_vec(),
+        Resolution::Theirs => theirs.to_vec(),
+        Resolution::Both => [ours, theirs].concat(),
+    };
+
+    [&lines[..hunk.start], &chosen[..], &lines[hunk.end + 1..]]
+        .concat()
+        .concat()
+}
+
+pub fn get_conflict_hunks(repo_path: &Path, file_path: &str) -> Result<Vec<ConflictHunk>> {
+    let path = workdir_path(repo_path, file_path)?;
+
+    // Binary files and deleted sides have nothing to parse
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(parse_conflicts(&content)),
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+pub fn resolve_conflict_hunk(
+    repo_path: &Path,
+    file_path: &str,
+    hunk_index: usize,
+    resolution: Resolution,
+) -> Result<()> {
+    let path = workdir_path(repo_path, file_path)?;
+    let content = std::fs::read_to_string(&path)?;
+    let hunks = parse_conflicts(&content);
+
+    match hunks.get(hunk_index) {
+        Some(hunk) => std::fs::write(path, resolve_hunk(&content, hunk, resolution))?,
+        None => return Err(anyhow::Error::msg("Conflict no longer exists")),
+    }
+
+    Ok(())
+}
+
+/// Takes one side of a conflict for the whole file, which also handles
+/// binary files and files deleted on one side.
+pub fn resolve_conflict_file(
+    repo_path: &Path,
+    file_path: &str,
+    resolution: Resolution,
+) -> Result<()> {
+    let repo = repo(repo_path)?;
+    let path = workdir_path(repo_path, file_path)?;
+
+    let conflict = repo
+        .index()?
+        .conflicts()?
+        .filter_map(|conflict| conflict.ok())
+        .find(|conflict| {
+            [&conflict.our, &conflict.their, &conflict.ancestor]
+                .iter()
+                .any(|entry| match entry {
+                    Some(entry) => entry.path == file_path.as_bytes(),
+                    None => false,
+                })
+        });
+
+    let conflict = match conflict {
+        Some(conflict) => conflict,
+        None => return Err(anyhow::anyhow!("{} is not conflicted", file_path)),
+    };
+
+    let entry = match resolution {
+        Resolution::Ours => conflict.our,
+        Resolution::Theirs => conflict.their,
+        Resolution::Both => {
+            return Err(anyhow::Error::msg(
+                "Both sides can only be taken per conflict",
+            ))
+        }
+    };
+
+    match entry {
+        Some(entry) => std::fs::write(&path, repo.find_blob(entry.id)?.content())?,
+        None if path.exists() => std::fs::remove_file(&path)?,
+        None => {}
+    }
+
+    mark_resolved(repo_path, file_path)
+}
+
+pub fn mark_resolved(repo_path: &Path, file_path: &str) -> Result<()> {
+    let repo = repo(repo_path)?;
+    let mut index = repo.index()?;
+    let path = Path::new(file_path);
+
+    if workdir_path(repo_path, file_path)?.exists() {
+        index.add_path(path)?;
+    } else {
+        index.remove_path(path)?;
+    }
+    index.write()?;
+
+    Ok(())
+}
+
+fn workdir_path(repo_path: &Path, file_path: &str) -> Result<std::path::PathBuf> {
+    let repo = repo(repo_path)?;
+    match repo.workdir() {
+        Some(workdir) => Ok(workdir.join(file_path)),
+        None => Err(anyhow::Error::msg("Repository has no working directory")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFLICTED: &str = "a
+<<<<<<< ours
+b
+||||||| base
+c
+=======
+d
+e
+>>>>>>> theirs
+f
+";
+
+    #[test]
+    fn parse_diff3() {
+        let hunks = parse_conflicts(CONFLICTED);
+
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].start, 1);
+        assert_eq!(hunks[0].end, 8);
+        assert_eq!(hunks[0].ours, vec!["b"]);
+        assert_eq!(hunks[0].base, vec!["c"]);
+        assert_eq!(hunks[0].theirs, vec!["d", "e"]);
+    }
+
+    #[test]
+    fn parse_without_base() {
+        let hunks = parse_conflicts("<<<<<<< HEAD\nb\n=======\nd\n>>>>>>> x\n");
+
+        assert_eq!(hunks.len(), 1);
+        assert!(hunks[0].base.is_empty());
+        assert_eq!(hunks[0].ours, vec!["b"]);
+        assert_eq!(hunks[0].theirs, vec!["d"]);
+    }
+
+    #[test]
+    fn parse_unterminated() {
+        assert!(parse_conflicts("<<<<<<< HEAD\nb\n=======\nd\n").is_empty());
+    }
+
+    #[test]
+    fn resolve_ours() {
+        let hunk = &parse_conflicts(CONFLICTED)[0];
+        assert_eq!(
+            resolve_hunk(CONFLICTED, hunk, Resolution::Ours),
+            "a\nb\nf\n"
+        );
+    }
+
+    #[test]
+    fn resolve_theirs() {
+        let hunk = &parse_conflicts(CONFLICTED)[0];
+        assert_eq!(
+            resolve_hunk(CONFLICTED, hunk, Resolution::Theirs),
+            "a\nd\ne\nf\n"
+        );
+    }
+
+    #[test]
+    fn resolve_both() {
+        let hunk = &parse_conflicts(CONFLICTED)[0];
+        assert_eq!(
+            resolve_hunk(CONFLICTED, hunk, Resolution::Both),
+            "a\nb\nd\ne\nf\n"
+        );
+    }
+
+    #[test]
+    fn resolve_keeps_line_endings() {
+        let content = "a\r\n<<<<<<< ours\r\nb\r\n=======\r\nd\r\n>>>>>>> theirs\r\nf";
+        let hunk = &parse_conflicts(content)[0];
+        assert_eq!(
+            resolve_hunk(content, hunk, Resolution::Both),
+            "a\r\nb\r\nd\r\nf"
+        );
+    }
+}
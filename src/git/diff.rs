@@ -1,7 +1,7 @@
 use std::path::Path;
 
 use anyhow::Result;
-use git2::{Diff, DiffFormat};
+use git2::{Delta, Diff, DiffDelta, DiffFormat, DiffHunk, DiffOptions, Oid, Patch, Repository};
 use tui::style::{Color, Style};
 
 use crate::git::repo;
@@ -17,14 +17,62 @@ pub struct DiffWindow {
 #[derive(Debug, PartialEq)]
 pub struct DiffLine {
     content: String,
+    file: String,
+    hunk: Option<HunkHeader>,
     origin: char,
     style: Style,
 }
 
+/// The `@@ -old_start,old_lines +new_start,new_lines @@` range of a hunk,
+/// used to find the same hunk again when building a patch to apply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HunkHeader {
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+}
+
+impl HunkHeader {
+    /// The header of the same hunk in a diff generated with `reverse(true)`.
+    pub fn reversed(&self) -> Self {
+        Self {
+            old_start: self.new_start,
+            old_lines: self.new_lines,
+            new_start: self.old_start,
+            new_lines: self.old_lines,
+        }
+    }
+}
+
+impl From<&DiffHunk<'_>> for HunkHeader {
+    fn from(hunk: &DiffHunk) -> Self {
+        Self {
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+        }
+    }
+}
+
+/// A file changed by a commit, with its `git diff --name-status` letter and
+/// `--numstat` counts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChangedFile {
+    pub path: String,
+    /// Where a renamed or copied file came from.
+    pub old_path: Option<String>,
+    pub status: char,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum DiffComponentType {
     Diff,
     Staged,
+    Stash(String),
 }
 
 impl DiffLine {
@@ -33,6 +81,8 @@ impl DiffLine {
 
         Self {
             content,
+            file: String::new(),
+            hunk: None,
             origin: 'H',
             style: Style::default(),
         }
@@ -42,6 +92,14 @@ impl DiffLine {
         self.origin
     }
 
+    pub fn file(&self) -> &String {
+        &self.file
+    }
+
+    pub fn hunk(&self) -> Option<HunkHeader> {
+        self.hunk
+    }
+
     pub fn style(&self) -> Style {
         self.style
     }
@@ -54,19 +112,22 @@ impl DiffLine {
 pub fn get_diff(repo_path: &Path, staged: bool) -> Result<Vec<DiffLine>> {
     let repo = repo(repo_path)?;
 
-    let mut diff_lines: Vec<DiffLine> = Vec::new();
-
-    let diff: Diff;
     let mut opt = git2::DiffOptions::new();
 
-    if staged {
+    let diff: Diff = if staged {
         let tree = repo.head()?.peel_to_tree()?;
-        diff = repo.diff_tree_to_index(Some(&tree), None, Some(&mut opt))?;
+        repo.diff_tree_to_index(Some(&tree), None, Some(&mut opt))?
     } else {
-        diff = repo.diff_index_to_workdir(None, Some(&mut opt))?;
-    }
+        repo.diff_index_to_workdir(None, Some(&mut opt))?
+    };
+
+    diff_lines(&diff)
+}
+
+pub fn diff_lines(diff: &Diff) -> Result<Vec<DiffLine>> {
+    let mut diff_lines: Vec<DiffLine> = Vec::new();
 
-    diff.print(DiffFormat::Patch, |_d, _h, l| {
+    diff.print(DiffFormat::Patch, |d, h, l| {
         if let Ok(diff_line) = std::str::from_utf8(l.content()) {
             let line_style = match l.origin() {
                 '-' => Style::default().fg(Color::Red),
@@ -77,6 +138,8 @@ pub fn get_diff(repo_path: &Path, staged: bool) -> Result<Vec<DiffLine>> {
 
             diff_lines.push(DiffLine {
                 content: diff_line.to_string(),
+                file: delta_path(&d),
+                hunk: h.as_ref().map(HunkHeader::from),
                 origin: l.origin(),
                 style: line_style,
             });
@@ -87,6 +150,103 @@ pub fn get_diff(repo_path: &Path, staged: bool) -> Result<Vec<DiffLine>> {
     Ok(diff_lines)
 }
 
+/// The diff of a commit against one of its parents, or against nothing for
+/// a root commit, with renames detected.
+fn commit_diff<'a>(
+    repo: &'a Repository,
+    id: &str,
+    parent: usize,
+    opts: &mut DiffOptions,
+) -> Result<Diff<'a>> {
+    let commit = repo.find_commit(Oid::from_str(id)?)?;
+    let parent_tree = match commit.parent_count() {
+        0 => None,
+        _ => Some(commit.parent(parent)?.tree()?),
+    };
+
+    let mut diff =
+        repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), Some(opts))?;
+    diff.find_similar(None)?;
+
+    Ok(diff)
+}
+
+/// The files a commit changed compared to the given parent.
+pub fn commit_files(repo_path: &Path, id: &str, parent: usize) -> Result<Vec<ChangedFile>> {
+    let repo = repo(repo_path)?;
+    let diff = commit_diff(&repo, id, parent, &mut DiffOptions::new())?;
+
+    let mut files = Vec::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let (_, insertions, deletions) = match Patch::from_diff(&diff, index)? {
+            Some(patch) => patch.line_stats()?,
+            // Binary files have no lines to count
+            None => (0, 0, 0),
+        };
+        let old_path = match delta.status() {
+            Delta::Renamed | Delta::Copied => delta
+                .old_file()
+                .path()
+                .map(|path| path.to_string_lossy().to_string()),
+            _ => None,
+        };
+
+        files.push(ChangedFile {
+            path: delta_path(&delta),
+            old_path,
+            status: status_letter(delta.status()),
+            insertions,
+            deletions,
+        });
+    }
+
+    Ok(files)
+}
+
+/// The patch of one file changed by a commit, compared to the given parent.
+pub fn commit_file_diff(
+    repo_path: &Path,
+    id: &str,
+    parent: usize,
+    file: &ChangedFile,
+) -> Result<Vec<DiffLine>> {
+    let repo = repo(repo_path)?;
+
+    let mut opts = DiffOptions::new();
+    opts.pathspec(&file.path).disable_pathspec_match(true);
+    // Both sides of a rename are needed to pair them up again
+    if let Some(old_path) = &file.old_path {
+        opts.pathspec(old_path);
+    }
+
+    let diff = commit_diff(&repo, id, parent, &mut opts)?;
+    diff_lines(&diff)
+}
+
+fn status_letter(status: Delta) -> char {
+    match status {
+        Delta::Added => 'A',
+        Delta::Deleted => 'D',
+        Delta::Renamed => 'R',
+        Delta::Copied => 'C',
+        Delta::Typechange => 'T',
+        _ => 'M',
+    }
+}
+
+fn delta_path(delta: &DiffDelta) -> String {
+    let file = if delta.status() == git2::Delta::Deleted {
+        delta.old_file()
+    } else {
+        delta.new_file()
+    };
+
+    match file.path() {
+        Some(path) => path.to_string_lossy().to_string(),
+        None => String::new(),
+    }
+}
+
 pub fn get_diff_stats(repo_path: &Path) -> Result<DiffWindow> {
     let repo = repo(repo_path)?;
 
@@ -0,0 +1,61 @@
+use std::path::Path;
+
+use anyhow::Result;
+use git2::build::CheckoutBuilder;
+use git2::{ApplyLocation, Diff};
+
+use crate::git::diff::HunkHeader;
+use crate::git::repo;
+use crate::git::stage::{apply_hunk, file_diff_options};
+
+/// Reverts the working tree copy of `file_path` back to the index version.
+pub fn discard_file(repo_path: &Path, file_path: &str) -> Result<()> {
+    let repo = repo(repo_path)?;
+
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force().update_index(false).path(file_path);
+
+    repo.checkout_index(None, Some(&mut checkout))?;
+
+    Ok(())
+}
+
+pub fn delete_untracked_file(repo_path: &Path, file_path: &str) -> Result<()> {
+    let repo = repo(repo_path)?;
+    let workdir = match repo.workdir() {
+        Some(workdir) => workdir,
+        None => return Err(anyhow::Error::msg("Repository has no working directory")),
+    };
+
+    // Untracked directories are listed as a whole, but a symlink to one is
+    // removed on its own
+    let path = workdir.join(file_path);
+    match std::fs::symlink_metadata(&path)?.is_dir() {
+        true => std::fs::remove_dir_all(&path)?,
+        false => std::fs::remove_file(&path)?,
+    }
+
+    Ok(())
+}
+
+pub fn discard_hunk(repo_path: &Path, file_path: &str, hunk: HunkHeader) -> Result<()> {
+    let repo = repo(repo_path)?;
+
+    // The reversed unstaged diff takes the working tree back to the index.
+    let mut opt = file_diff_options(file_path);
+    opt.reverse(true);
+    let diff = repo.diff_index_to_workdir(None, Some(&mut opt))?;
+
+    apply_hunk(&repo, &diff, hunk.reversed(), ApplyLocation::WorkDir)
+}
+
+/// Applies a reversed patch written by `git::patch::build_patch` to the
+/// working tree, leaving the index untouched.
+pub fn discard_patch(repo_path: &Path, patch: &str) -> Result<()> {
+    let repo = repo(repo_path)?;
+    let diff = Diff::from_buffer(patch.as_bytes())?;
+
+    repo.apply(&diff, ApplyLocation::WorkDir, None)?;
+
+    Ok(())
+}
@@ -2,28 +2,45 @@ use std::path::Path;
 
 use anyhow::Result;
 use crossbeam::channel::Sender;
-use git2::{FetchOptions, Repository};
+use git2::{AnnotatedCommit, FetchOptions, Oid, Repository};
 
+use crate::app::ProgramEvent;
+use crate::git::commit::merge_into_head;
 use crate::git::diff::head;
 use crate::git::repo;
 
 use super::{callbacks::create_remote_callbacks, remote::get_remote};
 
-pub fn pull_head(repo_path: &Path, _progress_sender: Sender<usize>) -> Result<()> {
+pub fn pull_head(
+    repo_path: &Path,
+    _progress_sender: Sender<usize>,
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<()> {
     let head = head(repo_path)?;
-    fetch(repo_path, _progress_sender)?;
-    merge(repo_path, &head)?;
-    Ok(())
+    pull(repo_path, &head, _progress_sender, event_sender)
 }
 
 pub fn pull_selected(
     repo_path: &Path,
     branch_name: &str,
     _progress_sender: Sender<usize>,
+    event_sender: &Sender<ProgramEvent>,
 ) -> Result<()> {
-    fetch(repo_path, _progress_sender)?;
-    merge(repo_path, branch_name)?;
-    Ok(())
+    pull(repo_path, branch_name, _progress_sender, event_sender)
+}
+
+/// Fetches from the remote the branch tracks, which needn't be the one
+/// `fetch` uses, then merges its upstream in.
+fn pull(
+    repo_path: &Path,
+    branch_name: &str,
+    _progress_sender: Sender<usize>,
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<()> {
+    let repo = repo(repo_path)?;
+    let remote = upstream_remote(&repo, branch_name)?;
+    fetch_remote(&repo, &remote, _progress_sender)?;
+    merge(repo_path, branch_name, event_sender)
 }
 
 pub fn fetch(repo_path: &Path, _progress_sender: Sender<usize>) -> Result<()> {
@@ -37,37 +54,57 @@ pub fn fetch(repo_path: &Path, _progress_sender: Sender<usize>) -> Result<()> {
         }
     };
 
+    fetch_remote(&repo, &remote, _progress_sender)
+}
+
+fn fetch_remote(repo: &Repository, remote: &str, _progress_sender: Sender<usize>) -> Result<()> {
     let callbacks = create_remote_callbacks(_progress_sender, None);
 
     let mut options = FetchOptions::new();
     options.download_tags(git2::AutotagOption::All);
     options.remote_callbacks(callbacks);
 
-    repo.find_remote(&remote)?
+    repo.find_remote(remote)?
         .fetch(&[] as &[&str], Some(&mut options), None)?;
 
     Ok(())
 }
 
 // Source: https://github.com/rust-lang/git2-rs/blob/master/examples/pull.rs
-fn merge(repo_path: &Path, branch_name: &str) -> Result<()> {
+fn merge(repo_path: &Path, branch_name: &str, event_sender: &Sender<ProgramEvent>) -> Result<()> {
     let repo = repo(repo_path)?;
 
-    let fetch_head = repo.find_reference("FETCH_HEAD")?;
-    let annotated_commit = repo.reference_to_annotated_commit(&fetch_head)?;
+    let annotated_commit = fetch_head(&repo, branch_name)?;
 
-    let (analysis, preference) = repo.merge_analysis(&[&annotated_commit])?;
+    let refname = format!("refs/heads/{}", branch_name);
+    let is_head = repo
+        .head()
+        .ok()
+        .is_some_and(|head| head.name() == Some(refname.as_str()));
+    let reference = repo.find_reference(&refname).ok();
+
+    // Compared against the branch pulled, which needn't be checked out
+    let (analysis, preference) = match &reference {
+        Some(reference) => repo.merge_analysis_for_ref(reference, &[&annotated_commit])?,
+        None => repo.merge_analysis(&[&annotated_commit])?,
+    };
 
     if analysis.is_fast_forward() {
         if preference.is_no_fast_forward() {
             return Err(anyhow::Error::msg("Fast forward merges are not allowed"));
         }
-        let refname = format!("refs/heads/{}", branch_name);
-        match repo.find_reference(&refname) {
-            Ok(mut r) => {
+        match reference {
+            Some(mut r) if is_head => {
                 ff_merge(&repo, &mut r, &annotated_commit)?;
             }
-            Err(_) => {
+            Some(mut r) => {
+                // Nothing is checked out, only the branch moves
+                r.set_target(
+                    annotated_commit.id(),
+                    &format!("pull: Fast-forward {}", branch_name),
+                )?;
+            }
+            None => {
                 // The branch doesn't exist so just set the reference to the
                 // commit directly. Usually this is because you are pulling
                 // into an empty repository.
@@ -87,12 +124,59 @@ fn merge(repo_path: &Path, branch_name: &str) -> Result<()> {
             }
         };
     } else if analysis.is_normal() {
-        normal_merge()?;
+        if !is_head {
+            return Err(anyhow::anyhow!(
+                "{} has diverged from its remote, check it out to merge",
+                branch_name
+            ));
+        }
+        merge_into_head(repo_path, &repo, annotated_commit, "pull", event_sender)?;
     }
 
     Ok(())
 }
 
+/// The FETCH_HEAD entry of the branch's upstream, as set by
+/// `branch.<name>.remote` and `branch.<name>.merge`.
+fn fetch_head<'a>(repo: &'a Repository, branch_name: &str) -> Result<AnnotatedCommit<'a>> {
+    let no_upstream = || anyhow::anyhow!("{} has no upstream branch to pull", branch_name);
+
+    let remote = repo.find_remote(&upstream_remote(repo, branch_name)?)?;
+    let remote_url = remote.url().unwrap_or("").to_string();
+    let merge = repo
+        .config()?
+        .get_string(&format!("branch.{}.merge", branch_name))
+        .map_err(|_| no_upstream())?;
+
+    let mut upstream: Option<(String, String, Oid)> = None;
+    repo.fetchhead_foreach(|name, url, oid, _| {
+        let url = String::from_utf8_lossy(url).to_string();
+        if name == merge && url == remote_url && upstream.is_none() {
+            upstream = Some((name.to_string(), url, *oid));
+        }
+        true
+    })?;
+
+    match upstream {
+        Some((name, url, oid)) => Ok(repo.annotated_commit_from_fetchhead(&name, &url, &oid)?),
+        None => Err(anyhow::anyhow!(
+            "Nothing was fetched for {} from {}",
+            merge,
+            remote.name().unwrap_or("")
+        )),
+    }
+}
+
+/// The remote named by `branch.<name>.remote`.
+fn upstream_remote(repo: &Repository, branch_name: &str) -> Result<String> {
+    let refname = format!("refs/heads/{}", branch_name);
+    let remote = repo
+        .branch_upstream_remote(&refname)
+        .map_err(|_| anyhow::anyhow!("{} has no upstream branch to pull", branch_name))?;
+
+    Ok(remote.as_str().unwrap_or("").to_string())
+}
+
 fn ff_merge(repo: &Repository, lb: &mut git2::Reference, rc: &git2::AnnotatedCommit) -> Result<()> {
     let name = match lb.name() {
         Some(s) => s.to_string(),
@@ -104,7 +188,3 @@ fn ff_merge(repo: &Repository, lb: &mut git2::Reference, rc: &git2::AnnotatedCom
     repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
     Ok(())
 }
-
-fn normal_merge() -> Result<()> {
-    Err(anyhow::Error::msg("Normal merge unimplemented"))
-}
@@ -0,0 +1,214 @@
+use std::collections::HashSet;
+
+/// One lane of a graph row: the lane's symbol followed by the line joining
+/// it to the lane on its right, and the lane it's coloured after.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphCell {
+    pub text: String,
+    pub lane: usize,
+}
+
+/// Lays out commits in lanes like `git log --graph`, one row per commit.
+/// Commits should be fed children before parents. A parent that came first,
+/// as one dated after its child does in the time ordered log, is left
+/// unjoined rather than keeping a lane open for it forever.
+#[derive(Default)]
+pub struct GraphBuilder {
+    /// The commits given rows so far.
+    drawn: HashSet<String>,
+    /// The commit each lane is waiting for.
+    lanes: Vec<Option<String>>,
+}
+
+impl GraphBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn row(&mut self, id: &str, parents: &[String]) -> Vec<GraphCell> {
+        self.drawn.insert(id.to_string());
+        let parents = parents
+            .iter()
+            .filter(|parent| !self.drawn.contains(*parent))
+            .collect::<Vec<_>>();
+
+        let waiting = self
+            .lanes
+            .iter()
+            .enumerate()
+            .filter(|(_, lane)| lane.as_deref() == Some(id))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        let column = match waiting.first() {
+            Some(column) => *column,
+            None => self.free_lane(&waiting),
+        };
+
+        let mut symbols = self
+            .lanes
+            .iter()
+            .map(|lane| match lane {
+                Some(_) => '│',
+                None => ' ',
+            })
+            .collect::<Vec<_>>();
+        symbols[column] = '●';
+
+        // Lanes joined to the commit by a horizontal line
+        let mut links = Vec::new();
+
+        // Children on other lanes fork from here
+        for &lane in waiting.iter().skip(1) {
+            symbols[lane] = if lane > column { '╯' } else { '╰' };
+            self.lanes[lane] = None;
+            links.push(lane);
+        }
+
+        self.lanes[column] = parents.first().map(|parent| parent.to_string());
+
+        // Merged parents join a lane already waiting for them, or start one
+        for &parent in parents.iter().skip(1) {
+            let existing = self
+                .lanes
+                .iter()
+                .position(|lane| lane.as_ref() == Some(parent));
+
+            match existing {
+                Some(lane) if lane != column => {
+                    symbols[lane] = if lane > column { '┤' } else { '├' };
+                    links.push(lane);
+                }
+                Some(_) => {}
+                None => {
+                    let lane = self.free_lane(&waiting);
+                    if lane >= symbols.len() {
+                        symbols.resize(lane + 1, ' ');
+                    }
+                    self.lanes[lane] = Some(parent.clone());
+                    symbols[lane] = if lane > column { '╮' } else { '╭' };
+                    links.push(lane);
+                }
+            }
+        }
+
+        let left = links.iter().copied().fold(column, usize::min);
+        let right = links.iter().copied().fold(column, usize::max);
+
+        let cells = symbols
+            .iter()
+            .enumerate()
+            .map(|(index, symbol)| {
+                let inside = index > left && index < right;
+                let crossed = inside && !links.contains(&index);
+                let symbol = match (inside, crossed, symbol) {
+                    (_, true, '│') => '┼',
+                    (_, true, ' ') => '─',
+                    // The line carries on past lanes joined in the middle
+                    (true, false, '╯' | '╰') => '┴',
+                    (true, false, '╮' | '╭') => '┬',
+                    (true, false, '┤' | '├') => '┼',
+                    (_, _, symbol) => *symbol,
+                };
+                let line = match index >= left && index < right {
+                    true => '─',
+                    false => ' ',
+                };
+                // Horizontal lines take the colour of the lane they lead to
+                let lane = match crossed {
+                    true if index < column => left,
+                    true => right,
+                    false => index,
+                };
+
+                GraphCell {
+                    text: format!("{}{}", symbol, line),
+                    lane,
+                }
+            })
+            .collect();
+
+        while let Some(None) = self.lanes.last() {
+            self.lanes.pop();
+        }
+
+        cells
+    }
+
+    /// The first empty lane, not counting lanes closed on this row.
+    fn free_lane(&mut self, closing: &[usize]) -> usize {
+        match self
+            .lanes
+            .iter()
+            .enumerate()
+            .position(|(index, lane)| lane.is_none() && !closing.contains(&index))
+        {
+            Some(index) => index,
+            None => {
+                self.lanes.push(None);
+                self.lanes.len() - 1
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(commits: &[(&str, &[&str])]) -> Vec<String> {
+        let mut graph = GraphBuilder::new();
+        commits
+            .iter()
+            .map(|(id, parents)| {
+                let parents = parents.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+                graph
+                    .row(id, &parents)
+                    .into_iter()
+                    .map(|cell| cell.text)
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn linear_history() {
+        let rows = render(&[("c", &["b"]), ("b", &["a"]), ("a", &[])]);
+
+        assert_eq!(rows, vec!["●", "●", "●"]);
+    }
+
+    #[test]
+    fn merge_and_fork() {
+        let rows = render(&[("m", &["b", "x"]), ("x", &["a"]), ("b", &["a"]), ("a", &[])]);
+
+        assert_eq!(rows, vec!["●─╮", "│ ●", "● │", "●─╯"]);
+    }
+
+    #[test]
+    fn crossing_lanes() {
+        let rows = render(&[
+            ("m", &["b", "y"]),
+            ("b", &["a"]),
+            ("n", &["a", "x"]),
+            ("x", &["a"]),
+            ("y", &["a"]),
+            ("a", &[]),
+        ]);
+
+        assert_eq!(
+            rows,
+            vec!["●─╮", "● │", "│ │ ●─╮", "│ │ │ ●", "│ ● │ │", "●─┴─┴─╯"]
+        );
+    }
+
+    #[test]
+    fn parent_before_child() {
+        // "a" is dated after "b" so the time ordered log shows it first
+        let rows = render(&[("c", &["a"]), ("a", &[]), ("b", &["a"]), ("d", &[])]);
+
+        assert_eq!(rows, vec!["●", "●", "●", "●"]);
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use anyhow::Result;
+use crossbeam::channel::Sender;
+use git2::Repository;
+
+use crate::app::{GitEvent, ProgramEvent};
+use crate::git::repo;
+
+/// The directory hooks are run from, `core.hooksPath` or `.git/hooks`.
+fn hooks_dir(repo: &Repository) -> PathBuf {
+    let hooks_path = repo
+        .config()
+        .and_then(|config| config.get_path("core.hooksPath"));
+
+    match hooks_path {
+        // Relative paths are relative to the top of the working tree, as
+        // hooks are run from there
+        Ok(path) if path.is_relative() => repo.workdir().unwrap_or(repo.path()).join(path),
+        Ok(path) => path,
+        Err(_) => repo.path().join("hooks"),
+    }
+}
+
+fn find_hook(repo: &Repository, name: &str) -> Option<PathBuf> {
+    let hook = hooks_dir(repo).join(name);
+
+    if hook.is_file() && is_executable(&hook) {
+        Some(hook)
+    } else {
+        None
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .map(|metadata| metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    true
+}
+
+/// Sends each line read to the hooks popup, returning everything read.
+fn stream_output<R: Read>(output: R, name: &str, event_sender: &Sender<ProgramEvent>) -> String {
+    let mut text = String::new();
+
+    for line in BufReader::new(output).lines().map_while(|line| line.ok()) {
+        event_sender
+            .send(ProgramEvent::Git(GitEvent::HookOutput(
+                name.to_string(),
+                line.clone(),
+            )))
+            .expect("Send failed.");
+        text.push_str(&line);
+        text.push('\n');
+    }
+
+    text
+}
+
+/// Runs a hook the way git does, from the top of the working tree, streaming
+/// its output to the hooks popup. Returns false when the hook isn't
+/// installed. A hook exiting non-zero is an error carrying its output.
+pub fn run_hook(
+    repo_path: &Path,
+    name: &str,
+    args: &[&str],
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<bool> {
+    let repo = repo(repo_path)?;
+    let hook = match find_hook(&repo, name) {
+        Some(hook) => hook,
+        None => return Ok(false),
+    };
+
+    let mut child = Command::new(&hook)
+        .args(args)
+        .current_dir(repo.workdir().unwrap_or(repo.path()))
+        .env("GIT_DIR", repo.path())
+        .env("GIT_INDEX_FILE", repo.path().join("index"))
+        // Hooks can't open an editor from inside the UI
+        .env("GIT_EDITOR", ":")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let stderr = child.stderr.take().map(|stderr| {
+        let name = name.to_string();
+        let event_sender = event_sender.clone();
+        thread::spawn(move || stream_output(stderr, &name, &event_sender))
+    });
+
+    let mut text = match child.stdout.take() {
+        Some(stdout) => stream_output(stdout, name, event_sender),
+        None => String::new(),
+    };
+    if let Some(stderr) = stderr {
+        text.push_str(&stderr.join().unwrap_or_default());
+    }
+
+    if !child.wait()?.success() {
+        return Err(anyhow::anyhow!(
+            "The {} hook failed:\n{}",
+            name,
+            text.trim_end()
+        ));
+    }
+
+    Ok(true)
+}
@@ -0,0 +1,204 @@
+use std::path::Path;
+
+use anyhow::Result;
+use git2::{Config, ConfigLevel, ErrorCode, Signature};
+
+use crate::git::repo;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+    pub signing_key: Option<String>,
+}
+
+/// A named identity to switch the repository to. Profiles are read from
+/// `buddy.identity.<profile>.name`, `.email` and `.signingKey`, and the
+/// global identity is offered as the `global` profile.
+#[derive(Clone, Debug)]
+pub struct IdentityProfile {
+    pub label: String,
+    pub identity: Identity,
+    /// Switching to it removes the repository's own identity.
+    pub is_global: bool,
+}
+
+/// The identity commits are made as right now, and where it comes from.
+#[derive(Clone, Debug)]
+pub struct CurrentIdentity {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub source: &'static str,
+}
+
+/// The committer, resolved the way git does: `GIT_COMMITTER_NAME` and
+/// `GIT_COMMITTER_EMAIL`, then `committer.*` and `user.*` from the
+/// repository's config, including its global and conditional includes.
+pub fn signature(repo_path: &Path) -> Result<Signature<'static>> {
+    resolve_signature(repo_path, "committer")
+}
+
+/// The author, resolved like [`signature`] from `GIT_AUTHOR_*` and
+/// `author.*`.
+pub fn author_signature(repo_path: &Path) -> Result<Signature<'static>> {
+    resolve_signature(repo_path, "author")
+}
+
+fn resolve_signature(repo_path: &Path, role: &str) -> Result<Signature<'static>> {
+    let config = repo(repo_path)?.config()?;
+    let name = resolve(&config, role, "name").map_or(String::new(), |(name, _)| name);
+    let email = resolve(&config, role, "email").map_or(String::new(), |(email, _)| email);
+
+    if name.trim().is_empty() || email.trim().is_empty() {
+        return Err(anyhow::Error::msg(
+            "There is no name and email to commit as. Set user.name and user.email, or pick an identity with i in Files.",
+        ));
+    }
+
+    Ok(Signature::now(name.trim(), email.trim())?)
+}
+
+/// The `name` or `email` of `role`, and where it was found.
+fn resolve(config: &Config, role: &str, key: &str) -> Option<(String, &'static str)> {
+    let env = format!("GIT_{}_{}", role.to_uppercase(), key.to_uppercase());
+    if let Ok(value) = std::env::var(env) {
+        return Some((value, "the environment"));
+    }
+
+    for name in [format!("{}.{}", role, key), format!("user.{}", key)] {
+        if let Ok(entry) = config.get_entry(&name) {
+            if let Some(value) = entry.value() {
+                let source = match entry.level() {
+                    ConfigLevel::Local => "the repository config",
+                    ConfigLevel::Global | ConfigLevel::XDG => "the global config",
+                    _ => "the system config",
+                };
+                return Some((value.to_string(), source));
+            }
+        }
+    }
+
+    match key {
+        "email" => std::env::var("EMAIL")
+            .ok()
+            .map(|email| (email, "the environment")),
+        _ => None,
+    }
+}
+
+/// The committer as [`signature`] would resolve it, for showing even when
+/// part of it is missing.
+pub fn current_identity(repo_path: &Path) -> Result<CurrentIdentity> {
+    let config = repo(repo_path)?.config()?;
+    let name = resolve(&config, "committer", "name");
+    let email = resolve(&config, "committer", "email");
+
+    let source = match (&name, &email) {
+        (Some((_, source)), _) | (None, Some((_, source))) => source,
+        (None, None) => "nowhere",
+    };
+
+    Ok(CurrentIdentity {
+        name: name.map(|(name, _)| name),
+        email: email.map(|(email, _)| email),
+        source,
+    })
+}
+
+pub fn identity_profiles(repo_path: &Path) -> Result<Vec<IdentityProfile>> {
+    let config = repo(repo_path)?.config()?.snapshot()?;
+    let mut profiles: Vec<IdentityProfile> = Vec::new();
+
+    let global = Config::open_default()
+        .and_then(|config| config.open_level(ConfigLevel::Global))
+        .ok()
+        .and_then(|global| {
+            Some(Identity {
+                name: global.get_string("user.name").ok()?,
+                email: global.get_string("user.email").ok()?,
+                signing_key: global.get_string("user.signingkey").ok(),
+            })
+        });
+    if let Some(identity) = global {
+        profiles.push(IdentityProfile {
+            label: "global".to_string(),
+            identity,
+            is_global: true,
+        });
+    }
+
+    let mut entries = config.entries(Some(r"^buddy\.identity\..*\.(name|email|signingkey)$"))?;
+    while let Some(entry) = entries.next() {
+        let entry = entry?;
+        let (key, value) = match (entry.name(), entry.value()) {
+            (Some(key), Some(value)) => (key, value.to_string()),
+            _ => continue,
+        };
+        let (label, field) = match key
+            .strip_prefix("buddy.identity.")
+            .and_then(|key| key.rsplit_once('.'))
+        {
+            Some(parts) => parts,
+            None => continue,
+        };
+
+        let index = match profiles
+            .iter()
+            .position(|profile| !profile.is_global && profile.label == label)
+        {
+            Some(index) => index,
+            None => {
+                profiles.push(IdentityProfile {
+                    label: label.to_string(),
+                    identity: Identity {
+                        name: String::new(),
+                        email: String::new(),
+                        signing_key: None,
+                    },
+                    is_global: false,
+                });
+                profiles.len() - 1
+            }
+        };
+
+        let identity = &mut profiles[index].identity;
+        match field {
+            "name" => identity.name = value,
+            "email" => identity.email = value,
+            _ => identity.signing_key = Some(value),
+        }
+    }
+
+    profiles
+        .retain(|profile| !profile.identity.name.is_empty() && !profile.identity.email.is_empty());
+    Ok(profiles)
+}
+
+/// Makes the repository commit as the profile's identity, by writing it to
+/// the repository's own config.
+pub fn set_identity(repo_path: &Path, profile: &IdentityProfile) -> Result<()> {
+    let mut config = repo(repo_path)?.config()?.open_level(ConfigLevel::Local)?;
+
+    if profile.is_global {
+        for key in ["user.name", "user.email", "user.signingkey"] {
+            remove_entry(&mut config, key)?;
+        }
+        return Ok(());
+    }
+
+    config.set_str("user.name", &profile.identity.name)?;
+    config.set_str("user.email", &profile.identity.email)?;
+    match &profile.identity.signing_key {
+        Some(key) => config.set_str("user.signingkey", key)?,
+        None => remove_entry(&mut config, "user.signingkey")?,
+    }
+
+    Ok(())
+}
+
+fn remove_entry(config: &mut Config, key: &str) -> Result<()> {
+    match config.remove(key) {
+        Err(err) if err.code() != ErrorCode::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,384 @@
+use std::path::Path;
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use regex::Regex;
+
+use crate::git::repo;
+
+/// Body lines are expected to wrap at this width.
+pub const BODY_WIDTH: usize = 72;
+
+const DEFAULT_TYPES: [&str; 11] = [
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert",
+];
+
+/// `type(scope)!: subject`, compiled once as the popup lints on every draw.
+static CONVENTIONAL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([a-z]+)(\([^()\s]+\))?!?: (.*)$").expect("valid regex"));
+
+/// Words that look like past tense or gerunds but are fine as the first word
+/// of a summary.
+const IMPERATIVE_EXCEPTIONS: [&str; 8] = [
+    "bring", "embed", "exceed", "feed", "need", "proceed", "seed", "speed",
+];
+
+/// Common verbs, to tell "Adds"/"Fixes" apart from words that just end in s.
+const COMMON_VERBS: [&str; 24] = [
+    "add",
+    "allow",
+    "bump",
+    "change",
+    "clean",
+    "create",
+    "drop",
+    "enable",
+    "ensure",
+    "fix",
+    "handle",
+    "implement",
+    "improve",
+    "make",
+    "merge",
+    "move",
+    "refactor",
+    "remove",
+    "rename",
+    "replace",
+    "revert",
+    "support",
+    "update",
+    "use",
+];
+
+/// Read from the `buddy.*` git config keys, so it can be set per repository.
+#[derive(Clone, Debug)]
+pub struct LintConfig {
+    /// `buddy.summaryLength`, summaries longer than this get a warning.
+    pub summary_length: usize,
+    /// `buddy.summaryMaxLength`, summaries longer than this block the
+    /// commit. `None` when set to 0, so long summaries only get a warning.
+    pub summary_max_length: Option<usize>,
+    /// `buddy.conventionalCommits`, require `type(scope): subject` summaries.
+    pub conventional: bool,
+    /// `buddy.commitTypes`, the comma separated types allowed by
+    /// conventional commits.
+    pub types: Vec<String>,
+    /// `buddy.imperativeMood`, warn about summaries like "Added x".
+    pub imperative: bool,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            summary_length: 50,
+            summary_max_length: Some(BODY_WIDTH),
+            conventional: false,
+            types: DEFAULT_TYPES.iter().map(|t| t.to_string()).collect(),
+            imperative: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Warning,
+    /// Blocks the commit.
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LintIssue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl LintIssue {
+    fn warning(message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+        }
+    }
+
+    fn error(message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+        }
+    }
+}
+
+pub fn lint_config(repo_path: &Path) -> Result<LintConfig> {
+    let config = repo(repo_path)?.config()?;
+    let mut lint_config = LintConfig::default();
+
+    if let Ok(length) = config.get_i64("buddy.summaryLength") {
+        lint_config.summary_length = length.max(1) as usize;
+    }
+    if let Ok(length) = config.get_i64("buddy.summaryMaxLength") {
+        lint_config.summary_max_length = match length > 0 {
+            true => Some(length as usize),
+            false => None,
+        };
+    }
+    if let Ok(conventional) = config.get_bool("buddy.conventionalCommits") {
+        lint_config.conventional = conventional;
+    }
+    if let Ok(types) = config.get_string("buddy.commitTypes") {
+        lint_config.types = types
+            .split(',')
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+    }
+    if let Ok(imperative) = config.get_bool("buddy.imperativeMood") {
+        lint_config.imperative = imperative;
+    }
+
+    Ok(lint_config)
+}
+
+pub fn lint_message(message: &str, config: &LintConfig) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let lines = message.lines().collect::<Vec<_>>();
+    let summary = lines.first().map(|line| line.trim()).unwrap_or("");
+
+    if summary.is_empty() {
+        issues.push(LintIssue::error("The summary is empty".to_string()));
+        return issues;
+    }
+
+    // Generated summaries like "Merge branch ..." are left alone
+    let generated = ["Merge ", "Revert ", "fixup! ", "squash! "]
+        .iter()
+        .any(|prefix| summary.starts_with(prefix));
+
+    let length = summary.chars().count();
+    if !generated {
+        match config.summary_max_length {
+            Some(max) if length > max => issues.push(LintIssue::error(format!(
+                "The summary is {} characters, keep it under {}",
+                length, max
+            ))),
+            _ if length > config.summary_length => issues.push(LintIssue::warning(format!(
+                "The summary is {} characters, aim for {}",
+                length, config.summary_length
+            ))),
+            _ => {}
+        }
+    }
+
+    if lines.len() > 1 && !lines[1].trim().is_empty() {
+        issues.push(LintIssue::error(
+            "Separate the summary from the body with a blank line".to_string(),
+        ));
+    }
+
+    let long_lines = lines
+        .iter()
+        .skip(1)
+        .filter(|line| line.chars().count() > BODY_WIDTH && !line.contains("://"))
+        .count();
+    if long_lines > 0 {
+        issues.push(LintIssue::warning(format!(
+            "{} body line(s) are longer than {} characters",
+            long_lines, BODY_WIDTH
+        )));
+    }
+
+    if generated {
+        return issues;
+    }
+
+    let subject = match config.conventional {
+        true => match conventional_subject(summary, &config.types) {
+            Ok(subject) => subject,
+            Err(issue) => {
+                issues.push(issue);
+                return issues;
+            }
+        },
+        false => summary,
+    };
+
+    if subject.ends_with('.') {
+        issues.push(LintIssue::warning(
+            "Leave the period off the end of the summary".to_string(),
+        ));
+    }
+
+    if config.imperative {
+        if let Some(word) = subject.split_whitespace().next() {
+            if !is_imperative(word) {
+                issues.push(LintIssue::warning(format!(
+                    "Use the imperative mood, \"{}\" reads like a description",
+                    word
+                )));
+            }
+        }
+    }
+
+    issues
+}
+
+pub fn has_errors(issues: &[LintIssue]) -> bool {
+    issues.iter().any(|issue| issue.severity == Severity::Error)
+}
+
+/// Sets the conventional commit type of a summary to the one after its
+/// current type, or the first one if it has none.
+pub fn cycle_type(summary: &str, types: &[String]) -> String {
+    let first = match types.first() {
+        Some(first) => first,
+        None => return summary.to_string(),
+    };
+
+    match parse_conventional(summary) {
+        Some((kind, _)) => {
+            let next = types
+                .iter()
+                .position(|t| t == kind)
+                .and_then(|index| types.get(index + 1))
+                .unwrap_or(first);
+            format!("{}{}", next, &summary[kind.len()..])
+        }
+        None => format!("{}: {}", first, summary),
+    }
+}
+
+/// Splits `type(scope)!: subject` into its type and subject.
+fn parse_conventional(summary: &str) -> Option<(&str, &str)> {
+    let captures = CONVENTIONAL.captures(summary)?;
+
+    Some((captures.get(1)?.as_str(), captures.get(3)?.as_str()))
+}
+
+fn conventional_subject<'a>(summary: &'a str, types: &[String]) -> Result<&'a str, LintIssue> {
+    let (kind, subject) = parse_conventional(summary).ok_or_else(|| {
+        LintIssue::error(format!(
+            "Use type(scope): subject, where type is one of {}",
+            types.join(", ")
+        ))
+    })?;
+
+    if !types.iter().any(|t| t == kind) {
+        return Err(LintIssue::error(format!(
+            "Unknown type \"{}\", use one of {}",
+            kind,
+            types.join(", ")
+        )));
+    }
+
+    if subject.trim().is_empty() {
+        return Err(LintIssue::error("The subject is empty".to_string()));
+    }
+
+    Ok(subject)
+}
+
+fn is_imperative(word: &str) -> bool {
+    let word = word.to_lowercase();
+
+    if IMPERATIVE_EXCEPTIONS.contains(&word.as_str()) {
+        return true;
+    }
+    if word.ends_with("ed") || word.ends_with("ing") {
+        return false;
+    }
+
+    let stems = [word.strip_suffix("es"), word.strip_suffix('s')];
+    !stems
+        .iter()
+        .flatten()
+        .any(|stem| COMMON_VERBS.contains(stem))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn conventional() -> LintConfig {
+        LintConfig {
+            conventional: true,
+            ..LintConfig::default()
+        }
+    }
+
+    #[test]
+    fn clean_message() {
+        let issues = lint_message(
+            "Add a commit linter\n\nIt checks the summary.",
+            &LintConfig::default(),
+        );
+
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn missing_blank_line_blocks() {
+        let issues = lint_message("Add a linter\nbody", &LintConfig::default());
+
+        assert!(has_errors(&issues));
+    }
+
+    #[test]
+    fn long_summary() {
+        let summary = "Add ".repeat(15);
+        let issues = lint_message(summary.trim(), &LintConfig::default());
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Warning);
+
+        let summary = "Add ".repeat(20);
+        assert!(has_errors(&lint_message(
+            summary.trim(),
+            &LintConfig::default()
+        )));
+    }
+
+    #[test]
+    fn long_generated_summary() {
+        let summary = format!("Merge branch '{}' into main", "feature/".repeat(10));
+        assert!(lint_message(&summary, &LintConfig::default()).is_empty());
+
+        let config = LintConfig {
+            summary_max_length: None,
+            ..LintConfig::default()
+        };
+        let summary = "Add ".repeat(20);
+        assert!(!has_errors(&lint_message(summary.trim(), &config)));
+    }
+
+    #[test]
+    fn imperative_mood() {
+        assert!(is_imperative("Add"));
+        assert!(is_imperative("Process"));
+        assert!(is_imperative("Embed"));
+        assert!(!is_imperative("Added"));
+        assert!(!is_imperative("Fixes"));
+        assert!(!is_imperative("adding"));
+    }
+
+    #[test]
+    fn conventional_commits() {
+        assert!(lint_message("feat(log): add a search", &conventional()).is_empty());
+        assert!(lint_message("fix!: drop the old flag", &conventional()).is_empty());
+        assert!(has_errors(&lint_message("add a search", &conventional())));
+        assert!(has_errors(&lint_message("feature: add", &conventional())));
+
+        let issues = lint_message("feat: added a search", &conventional());
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn cycle_types() {
+        let types = LintConfig::default().types;
+
+        assert_eq!(cycle_type("add x", &types), "feat: add x");
+        assert_eq!(cycle_type("feat: add x", &types), "fix: add x");
+        assert_eq!(cycle_type("fix(log)!: add x", &types), "docs(log)!: add x");
+        assert_eq!(cycle_type("revert: add x", &types), "feat: add x");
+    }
+}
@@ -1,11 +1,34 @@
-use std::path::Path;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::thread;
 
 use anyhow::Result;
-use git2::Oid;
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use git2::{Delta, Diff, DiffFindOptions, DiffOptions, Oid, Repository, Revwalk, Sort, Tree};
 
+use crate::app::{GitEvent, ProgramEvent};
 use crate::git::repo;
+use crate::git::sign::SignatureStatus;
 use crate::git::time::CommitDate;
 
+/// What kind of ref points at a commit, in the order they're listed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RefKind {
+    Head,
+    Branch,
+    Remote,
+    Tag,
+}
+
+/// A ref pointing at a commit, named the way `git log --decorate` does.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RefLabel {
+    pub kind: RefKind,
+    pub name: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct Commit {
     id: String,
@@ -13,6 +36,9 @@ pub struct Commit {
     email: String,
     message_summary: String,
     message_body: Vec<String>,
+    parents: Vec<String>,
+    refs: Vec<RefLabel>,
+    signature: SignatureStatus,
     time: CommitDate,
 }
 
@@ -24,6 +50,9 @@ impl Commit {
             email: String::new(),
             message_summary: String::new(),
             message_body: Vec::new(),
+            parents: Vec::new(),
+            refs: Vec::new(),
+            signature: SignatureStatus::Unsigned,
             time: CommitDate::new(git2::Time::new(0, 0)),
         }
     }
@@ -47,7 +76,13 @@ impl Commit {
             Some(body) => body.split('\n').map(|line| line.to_string()).collect(),
             None => Vec::new(),
         };
+        let parents = commit.parent_ids().map(|id| id.to_string()).collect();
         let time = CommitDate::new(commit.time());
+        // Checking the signature is slow, it's verified separately
+        let signature = match commit.header_field_bytes("gpgsig") {
+            Ok(_) => SignatureStatus::Unchecked,
+            Err(_) => SignatureStatus::Unsigned,
+        };
 
         if message_summary.len() > 70 {
             let index = get_split_index(&message_summary);
@@ -62,6 +97,9 @@ impl Commit {
             email,
             message_summary,
             message_body,
+            parents,
+            refs: Vec::new(),
+            signature,
             time,
         }
     }
@@ -86,6 +124,22 @@ impl Commit {
         &self.message_body
     }
 
+    pub fn parents(&self) -> &[String] {
+        &self.parents
+    }
+
+    pub fn refs(&self) -> &[RefLabel] {
+        &self.refs
+    }
+
+    pub fn signature(&self) -> &SignatureStatus {
+        &self.signature
+    }
+
+    pub fn set_signature(&mut self, signature: SignatureStatus) {
+        self.signature = signature;
+    }
+
     pub fn shorthand_id(&self) -> String {
         self.id[0..8].to_string()
     }
@@ -97,24 +151,369 @@ impl Commit {
 
 pub fn collect_commits(repo_path: &Path, oid: Oid) -> Result<Vec<Commit>> {
     let repo = repo(repo_path)?;
+    let mut revwalk = revwalk(&repo)?;
+    revwalk.push(oid)?;
 
-    let mut history: Vec<Commit> = Vec::new();
+    walk(&repo, revwalk)
+}
+
+/// A single commit, with the refs pointing at it.
+pub fn find_commit(repo_path: &Path, id: &str) -> Result<Commit> {
+    let repo = repo(repo_path)?;
+    let mut refs = ref_map(&repo)?;
+
+    decorated_commit(&repo, Oid::from_str(id)?, &mut refs)
+}
+
+/// Where the log starts walking the history from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogScope {
+    Head,
+    /// HEAD and every branch, remote branch and tag, like `git log --all`.
+    AllRefs,
+}
+
+/// Limits the log to the commits changing a file or directory, like `git
+/// log -- <path>`. Following renames tracks a file back through its old
+/// names, like `git log --follow`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathFilter {
+    pub path: String,
+    pub follow: bool,
+}
+
+impl PathFilter {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            follow: false,
+        }
+    }
+
+    /// Whether a commit changes the path. Merges only count when they differ
+    /// from every parent. Commits have to be checked in the order of the
+    /// log, as `path` moves to the old name when a rename is passed.
+    pub fn touches(&mut self, repo: &Repository, commit: &git2::Commit) -> Result<bool> {
+        let tree = commit.tree()?;
+        let parents = commit
+            .parents()
+            .map(|parent| parent.tree())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let first_parent = path_diff(repo, parents.first(), &tree, &self.path)?;
+        if first_parent.deltas().len() == 0 {
+            return Ok(false);
+        }
+        for parent in parents.iter().skip(1) {
+            if path_diff(repo, Some(parent), &tree, &self.path)?
+                .deltas()
+                .len()
+                == 0
+            {
+                return Ok(false);
+            }
+        }
+
+        let added = first_parent
+            .deltas()
+            .any(|delta| delta.status() == Delta::Added);
+        if self.follow && added {
+            if let Some(parent) = parents.first() {
+                self.follow_rename(repo, parent, &tree)?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Moves on to the file's old name if it was added by renaming it.
+    fn follow_rename(&mut self, repo: &Repository, parent: &Tree, tree: &Tree) -> Result<()> {
+        let mut diff = repo.diff_tree_to_tree(Some(parent), Some(tree), None)?;
+        diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+        let old_path = diff
+            .deltas()
+            .find(|delta| {
+                delta.status() == Delta::Renamed
+                    && delta.new_file().path() == Some(Path::new(&self.path))
+            })
+            .and_then(|delta| delta.old_file().path().map(|path| path.to_path_buf()));
+        if let Some(old_path) = old_path {
+            self.path = old_path.to_string_lossy().to_string();
+        }
+
+        Ok(())
+    }
+}
+
+fn path_diff<'r>(
+    repo: &'r Repository,
+    old: Option<&Tree>,
+    new: &Tree,
+    path: &str,
+) -> Result<Diff<'r>> {
+    let mut options = DiffOptions::new();
+    options.pathspec(path);
+
+    Ok(repo.diff_tree_to_tree(old, Some(new), Some(&mut options))?)
+}
+
+/// Walks the history in the background, a page at a time. Each count sent
+/// on the returned channel loads that many more commits, which come back as
+/// `GitEvent::CommitsLoaded` tagged with `generation`. The walk stops when
+/// the history runs out or the channel is dropped.
+pub fn page_commits(
+    repo_path: PathBuf,
+    scope: LogScope,
+    paths: Option<PathFilter>,
+    generation: usize,
+    event_sender: Sender<ProgramEvent>,
+) -> Sender<usize> {
+    let (page_sender, page_receiver) = unbounded();
+
+    thread::spawn(move || {
+        let result = send_pages(
+            &repo_path,
+            scope,
+            paths,
+            generation,
+            &page_receiver,
+            &event_sender,
+        );
+        if let Err(err) = result {
+            event_sender
+                .send(ProgramEvent::Error(err))
+                .expect("Send failed.");
+        }
+    });
+
+    page_sender
+}
+
+fn send_pages(
+    repo_path: &Path,
+    scope: LogScope,
+    mut paths: Option<PathFilter>,
+    generation: usize,
+    pages: &Receiver<usize>,
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<()> {
+    let repo = repo(repo_path)?;
+    let mut refs = ref_map(&repo)?;
+    let mut oids = history(&repo, scope)?.flatten();
+
+    for count in pages.iter() {
+        let mut commits = Vec::with_capacity(count);
+        while commits.len() < count {
+            let oid = match oids.next() {
+                Some(oid) => oid,
+                None => break,
+            };
+            if let Some(paths) = &mut paths {
+                if !paths.touches(&repo, &repo.find_commit(oid)?)? {
+                    continue;
+                }
+            }
+            commits.push(decorated_commit(&repo, oid, &mut refs)?);
+        }
+
+        let finished = commits.len() < count;
+        event_sender
+            .send(ProgramEvent::Git(GitEvent::CommitsLoaded(
+                generation, commits, finished,
+            )))
+            .expect("Send failed.");
+
+        if finished {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// A hash of where HEAD and every ref point, to tell when the history shown
+/// needs reloading.
+pub fn refs_fingerprint(repo_path: &Path) -> Result<u64> {
+    let repo = repo(repo_path)?;
+    let mut hasher = DefaultHasher::new();
+
+    if let Ok(head) = repo.head() {
+        head.name().hash(&mut hasher);
+        head.target().hash(&mut hasher);
+    }
+    for reference in repo.references()?.flatten() {
+        reference.name().hash(&mut hasher);
+        reference.target().hash(&mut hasher);
+        reference.symbolic_target().hash(&mut hasher);
+    }
+
+    Ok(hasher.finish())
+}
+
+/// Walks the commits of a scope in the order the log shows them.
+pub fn history(repo: &Repository, scope: LogScope) -> Result<TimeWalk<'_>> {
+    let mut walk = TimeWalk {
+        repo,
+        queue: BinaryHeap::new(),
+        seen: HashSet::new(),
+    };
+    walk.push(repo.head()?.peel_to_commit()?.id())?;
+
+    if scope == LogScope::AllRefs {
+        for reference in repo.references()?.flatten() {
+            let name = reference.name().unwrap_or("");
+            if !["refs/heads/", "refs/remotes/", "refs/tags/"]
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+            {
+                continue;
+            }
+            // Tags of trees and blobs have no history
+            if let Ok(commit) = reference.peel_to_commit() {
+                walk.push(commit.id())?;
+            }
+        }
+    }
+
+    Ok(walk)
+}
+
+/// Walks the history newest first, like `git log` without `--topo-order`.
+/// libgit2's sorted walks go through the whole history before returning the
+/// first commit, this only queues the parents of the commits returned so
+/// far. Children come before their parents unless a commit is dated before
+/// its parent, which the graph copes with.
+pub struct TimeWalk<'repo> {
+    repo: &'repo Repository,
+    /// The commits waiting to be returned, by commit time.
+    queue: BinaryHeap<(i64, Oid)>,
+    seen: HashSet<Oid>,
+}
+
+impl TimeWalk<'_> {
+    fn push(&mut self, oid: Oid) -> Result<(), git2::Error> {
+        if self.seen.insert(oid) {
+            let commit = self.repo.find_commit(oid)?;
+            self.queue.push((commit.time().seconds(), oid));
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for TimeWalk<'_> {
+    type Item = Result<Oid, git2::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_, oid) = self.queue.pop()?;
+        let commit = match self.repo.find_commit(oid) {
+            Ok(commit) => commit,
+            Err(err) => return Some(Err(err)),
+        };
+
+        for parent in commit.parent_ids() {
+            if let Err(err) = self.push(parent) {
+                return Some(Err(err));
+            }
+        }
+        Some(Ok(oid))
+    }
+}
+
+fn revwalk(repo: &Repository) -> Result<Revwalk<'_>> {
     let mut revwalk = repo.revwalk()?;
 
     revwalk.reset()?;
-    revwalk.push(oid)?;
+    // Children before parents, so the graph can be drawn
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+
+    Ok(revwalk)
+}
 
+fn walk(repo: &Repository, revwalk: Revwalk<'_>) -> Result<Vec<Commit>> {
+    let mut refs = ref_map(repo)?;
+    let mut history: Vec<Commit> = Vec::new();
     let oids: Vec<Result<Oid, git2::Error>> = revwalk.collect();
-    for oid in oids {
-        if oid.is_ok() {
-            let commit = repo.find_commit(oid.unwrap())?;
-            history.push(Commit::from_git_commit(commit));
-        }
+    for oid in oids.into_iter().flatten() {
+        history.push(decorated_commit(repo, oid, &mut refs)?);
     }
 
     Ok(history)
 }
 
+/// Loads a commit with the refs pointing at it, taken out of `refs` as
+/// each commit is only seen once.
+pub fn decorated_commit(
+    repo: &Repository,
+    oid: Oid,
+    refs: &mut HashMap<Oid, Vec<RefLabel>>,
+) -> Result<Commit> {
+    let mut commit = Commit::from_git_commit(repo.find_commit(oid)?);
+    commit.refs = refs.remove(&oid).unwrap_or_default();
+
+    Ok(commit)
+}
+
+/// The branches, remote branches and tags pointing at each commit, with
+/// HEAD folded into the branch it's on as `HEAD -> branch`.
+pub fn ref_map(repo: &Repository) -> Result<HashMap<Oid, Vec<RefLabel>>> {
+    let mut refs: HashMap<Oid, Vec<RefLabel>> = HashMap::new();
+
+    let head = repo.head().ok();
+    let head_branch = head
+        .as_ref()
+        .filter(|head| head.is_branch())
+        .and_then(|head| head.name().map(|name| name.to_string()));
+    if let Some(id) = head.as_ref().and_then(|head| head.target()) {
+        let name = match head.as_ref().and_then(|head| head.shorthand()) {
+            Some(branch) if head_branch.is_some() => format!("HEAD -> {}", branch),
+            _ => "HEAD".to_string(),
+        };
+        refs.entry(id).or_default().push(RefLabel {
+            kind: RefKind::Head,
+            name,
+        });
+    }
+
+    for reference in repo.references()?.flatten() {
+        let (name, shorthand) = match (reference.name(), reference.shorthand()) {
+            (Some(name), Some(shorthand)) => (name, shorthand),
+            _ => continue,
+        };
+        // origin/HEAD and the like only repeat another ref
+        if reference.symbolic_target().is_some() || Some(name) == head_branch.as_deref() {
+            continue;
+        }
+
+        let label = match name {
+            name if name.starts_with("refs/heads/") => RefLabel {
+                kind: RefKind::Branch,
+                name: shorthand.to_string(),
+            },
+            name if name.starts_with("refs/remotes/") => RefLabel {
+                kind: RefKind::Remote,
+                name: shorthand.to_string(),
+            },
+            name if name.starts_with("refs/tags/") => RefLabel {
+                kind: RefKind::Tag,
+                name: format!("tag: {}", shorthand),
+            },
+            _ => continue,
+        };
+
+        // Annotated tags point at the tag object, not the commit
+        if let Ok(commit) = reference.peel_to_commit() {
+            refs.entry(commit.id()).or_default().push(label);
+        }
+    }
+
+    for labels in refs.values_mut() {
+        labels.sort_by(|a, b| a.kind.cmp(&b.kind).then(a.name.cmp(&b.name)));
+    }
+
+    Ok(refs)
+}
+
 fn get_split_index(message_summary: &str) -> usize {
     // what in tarnation is this?
     let (mut i, mut c) = message_summary
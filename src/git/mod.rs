@@ -5,16 +5,30 @@ use git2::{Repository, RepositoryOpenFlags};
 
 use crate::git::commit::create_initial_commit;
 
+pub mod blame;
 pub mod branch;
 pub mod callbacks;
 pub mod commit;
+pub mod conflict;
 pub mod diff;
+pub mod discard;
 pub mod fetch;
+pub mod graph;
+pub mod hooks;
+pub mod identity;
+pub mod lint;
 pub mod log;
+pub mod patch;
+pub mod rebase;
 pub mod remote;
+pub mod search;
+pub mod sign;
 pub mod stage;
+pub mod stash;
+pub mod state;
 pub mod status;
 pub mod time;
+pub mod trailer;
 
 pub fn repo(repo_path: &Path) -> Result<Repository> {
     let repo = Repository::open_ext(repo_path, RepositoryOpenFlags::empty(), Vec::<&Path>::new())?;
@@ -22,7 +36,7 @@ pub fn repo(repo_path: &Path) -> Result<Repository> {
 }
 
 pub fn init_new_repo(repo_path: &Path) -> Result<()> {
-    Repository::init(&repo_path)?;
+    Repository::init(repo_path)?;
     create_initial_commit(repo_path)?;
     Ok(())
 }
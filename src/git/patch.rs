@@ -0,0 +1,221 @@
+use crate::git::diff::HunkHeader;
+
+pub struct PatchLine<'a> {
+    pub origin: char,
+    pub content: &'a str,
+    pub selected: bool,
+}
+
+pub struct PatchHunk<'a> {
+    pub header: HunkHeader,
+    pub lines: Vec<PatchLine<'a>>,
+}
+
+/// Writes a patch for `file` that only contains the selected `+`/`-` lines.
+///
+/// Unselected additions are dropped and unselected deletions are kept as
+/// context, so the result applies cleanly to the old side of the diff. When
+/// `reverse` is set the hunks are inverted first, which turns a staged
+/// (HEAD to index) diff into a patch that takes the lines back out of the index.
+///
+/// Returns `None` if nothing in the hunks is selected.
+pub fn build_patch(file: &str, hunks: &[PatchHunk], reverse: bool) -> Option<String> {
+    let mut patch = format!("diff --git a/{file} b/{file}\n--- a/{file}\n+++ b/{file}\n");
+    let mut offset: i64 = 0;
+    let mut has_changes = false;
+
+    for hunk in hunks {
+        let header = if reverse {
+            hunk.header.reversed()
+        } else {
+            hunk.header
+        };
+
+        let mut body = String::new();
+        let mut old_lines: i64 = 0;
+        let mut new_lines: i64 = 0;
+        let mut hunk_has_changes = false;
+        let mut last_kept = false;
+
+        for line in &hunk.lines {
+            let origin = match (line.origin, reverse) {
+                ('+', true) => '-',
+                ('-', true) => '+',
+                (origin, _) => origin,
+            };
+
+            let origin = match origin {
+                '+' if line.selected => '+',
+                '+' => {
+                    last_kept = false;
+                    continue;
+                }
+                '-' if line.selected => '-',
+                '-' | ' ' => ' ',
+                // "\ No newline at end of file" belongs to the line before it
+                '=' | '>' | '<' => {
+                    if last_kept {
+                        body.push_str("\\ No newline at end of file\n");
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+
+            match origin {
+                '+' => new_lines += 1,
+                '-' => old_lines += 1,
+                _ => {
+                    old_lines += 1;
+                    new_lines += 1;
+                }
+            }
+
+            if origin != ' ' {
+                hunk_has_changes = true;
+            }
+
+            body.push(origin);
+            body.push_str(line.content);
+            if !line.content.ends_with('\n') {
+                body.push('\n');
+            }
+            last_kept = true;
+        }
+
+        // Hunks left with only context would be rejected by `apply`
+        if !hunk_has_changes {
+            continue;
+        }
+        has_changes = true;
+
+        let old_start = header.old_start as i64;
+        let mut new_start = old_start + offset;
+        if old_lines == 0 {
+            new_start += 1;
+        } else if new_lines == 0 {
+            new_start -= 1;
+        }
+        offset += new_lines - old_lines;
+
+        patch.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start,
+            old_lines,
+            new_start.max(0),
+            new_lines
+        ));
+        patch.push_str(&body);
+    }
+
+    if has_changes {
+        Some(patch)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn header(old_start: u32, old_lines: u32, new_start: u32, new_lines: u32) -> HunkHeader {
+        HunkHeader {
+            old_start,
+            old_lines,
+            new_start,
+            new_lines,
+        }
+    }
+
+    fn line(origin: char, content: &str, selected: bool) -> PatchLine<'_> {
+        PatchLine {
+            origin,
+            content,
+            selected,
+        }
+    }
+
+    #[test]
+    fn nothing_selected() {
+        let hunks = vec![PatchHunk {
+            header: header(1, 2, 1, 2),
+            lines: vec![
+                line(' ', "a\n", false),
+                line('-', "b\n", false),
+                line('+', "c\n", false),
+            ],
+        }];
+
+        assert_eq!(build_patch("f", &hunks, false), None);
+    }
+
+    #[test]
+    fn single_addition() {
+        let hunks = vec![PatchHunk {
+            header: header(1, 2, 1, 3),
+            lines: vec![
+                line(' ', "a\n", false),
+                line('+', "b\n", true),
+                line('+', "c\n", false),
+                line(' ', "d\n", false),
+            ],
+        }];
+
+        let patch = build_patch("f", &hunks, false).unwrap();
+        assert!(patch.ends_with("@@ -1,2 +1,3 @@\n a\n+b\n d\n"));
+    }
+
+    #[test]
+    fn unselected_deletion_becomes_context() {
+        let hunks = vec![PatchHunk {
+            header: header(1, 3, 1, 1),
+            lines: vec![
+                line(' ', "a\n", false),
+                line('-', "b\n", true),
+                line('-', "c\n", false),
+            ],
+        }];
+
+        let patch = build_patch("f", &hunks, false).unwrap();
+        assert!(patch.ends_with("@@ -1,3 +1,2 @@\n a\n-b\n c\n"));
+    }
+
+    #[test]
+    fn reverse_swaps_sides() {
+        let hunks = vec![PatchHunk {
+            header: header(1, 2, 1, 3),
+            lines: vec![
+                line(' ', "a\n", false),
+                line('+', "b\n", true),
+                line('+', "c\n", false),
+                line(' ', "d\n", false),
+            ],
+        }];
+
+        let patch = build_patch("f", &hunks, true).unwrap();
+        assert!(patch.ends_with("@@ -1,4 +1,3 @@\n a\n-b\n c\n d\n"));
+    }
+
+    #[test]
+    fn later_hunks_are_offset() {
+        let hunks = vec![
+            PatchHunk {
+                header: header(1, 1, 1, 3),
+                lines: vec![
+                    line(' ', "a\n", false),
+                    line('+', "b\n", true),
+                    line('+', "c\n", true),
+                ],
+            },
+            PatchHunk {
+                header: header(10, 2, 12, 1),
+                lines: vec![line(' ', "x\n", false), line('-', "y\n", true)],
+            },
+        ];
+
+        let patch = build_patch("f", &hunks, false).unwrap();
+        assert!(patch.contains("@@ -1,1 +1,3 @@\n"));
+        assert!(patch.contains("@@ -10,2 +12,1 @@\n"));
+    }
+}
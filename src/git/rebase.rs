@@ -0,0 +1,557 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use crossbeam::channel::Sender;
+use git2::build::CheckoutBuilder;
+use git2::{Oid, Rebase, RebaseOptions, Repository, ResetType, Sort, StatusOptions};
+
+use crate::app::ProgramEvent;
+use crate::git::commit::{commit_to_head, conflict_checkout};
+use crate::git::identity::signature;
+use crate::git::repo;
+use crate::git::state::ensure_clean_state;
+use crate::git::status::get_conflicted_paths;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RebaseAction {
+    Pick,
+    Reword,
+    Edit,
+    Squash,
+    Fixup,
+    Drop,
+}
+
+impl RebaseAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RebaseAction::Pick => "pick",
+            RebaseAction::Reword => "reword",
+            RebaseAction::Edit => "edit",
+            RebaseAction::Squash => "squash",
+            RebaseAction::Fixup => "fixup",
+            RebaseAction::Drop => "drop",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            RebaseAction::Pick,
+            RebaseAction::Reword,
+            RebaseAction::Edit,
+            RebaseAction::Squash,
+            RebaseAction::Fixup,
+            RebaseAction::Drop,
+        ]
+        .into_iter()
+        .find(|action| action.name() == name)
+    }
+}
+
+/// One line of an interactive rebase. `message` replaces the commit message
+/// when rewording.
+#[derive(Clone, Debug)]
+pub struct RebaseTodo {
+    pub action: RebaseAction,
+    pub id: String,
+    pub summary: String,
+    pub message: Option<String>,
+}
+
+/// The commits after `base` up to HEAD, oldest first, all picked.
+pub fn get_rebase_todo(repo_path: &Path, base: &str) -> Result<Vec<RebaseTodo>> {
+    let repo = repo(repo_path)?;
+    let base = Oid::from_str(base)?;
+    let head = repo.head()?.peel_to_commit()?.id();
+
+    if head != base && !repo.graph_descendant_of(head, base)? {
+        return Err(anyhow::Error::msg(
+            "The base commit is not an ancestor of HEAD",
+        ));
+    }
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    revwalk.push(head)?;
+    revwalk.hide(base)?;
+
+    let mut todo = Vec::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+
+        if commit.parent_count() > 1 {
+            return Err(anyhow::Error::msg(
+                "Rebasing merge commits is not supported",
+            ));
+        }
+
+        todo.push(RebaseTodo {
+            action: RebaseAction::Pick,
+            id: commit.id().to_string(),
+            summary: commit.summary().unwrap_or("").to_string(),
+            message: None,
+        });
+    }
+
+    Ok(todo)
+}
+
+/// The message of a commit with its summary, the first paragraph, replaced
+/// and the rest kept as it is.
+pub fn reworded_message(repo_path: &Path, id: &str, summary: &str) -> Result<String> {
+    let repo = repo(repo_path)?;
+    let commit = repo.find_commit(Oid::from_str(id)?)?;
+    let message = commit.message().unwrap_or("").trim_start_matches('\n');
+
+    let body = message
+        .split_once("\n\n")
+        .map_or("", |(_, body)| body.trim_start_matches('\n'));
+    match body.trim().is_empty() {
+        true => Ok(summary.to_string()),
+        false => Ok(format!("{}\n\n{}", summary, body)),
+    }
+}
+
+/// Replays `todo` on top of `base`. Each line runs as its own libgit2 rebase
+/// of a single commit, which is what lets lines be reordered. The rest of the
+/// todo list is kept in the git directory so the rebase can stop for edits
+/// and conflicts and be continued or aborted later.
+pub fn start_rebase(
+    repo_path: &Path,
+    base: &str,
+    todo: &[RebaseTodo],
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<()> {
+    ensure_clean_state(repo_path)?;
+    let repo = repo(repo_path)?;
+
+    let mut options = StatusOptions::new();
+    options.include_untracked(false);
+    if !repo.statuses(Some(&mut options))?.is_empty() {
+        return Err(anyhow::Error::msg(
+            "Commit or stash your changes before rebasing",
+        ));
+    }
+
+    let first = todo.iter().find(|entry| entry.action != RebaseAction::Drop);
+    if let Some(entry) = first {
+        if matches!(entry.action, RebaseAction::Squash | RebaseAction::Fixup) {
+            return Err(anyhow::anyhow!(
+                "Can't {} {} without a previous commit",
+                entry.action.name(),
+                &entry.id[0..7]
+            ));
+        }
+    }
+
+    let head = repo.head()?;
+    let head_name = match head.is_branch() {
+        true => head.name().unwrap_or("HEAD").to_string(),
+        false => "detached HEAD".to_string(),
+    };
+    let orig_head = head.peel_to_commit()?.id();
+
+    let state_dir = todo_dir(&repo);
+    fs::create_dir_all(&state_dir)?;
+    fs::write(state_dir.join("head-name"), head_name)?;
+    fs::write(state_dir.join("orig-head"), orig_head.to_string())?;
+    write_todo(&state_dir, todo)?;
+
+    let base = repo.find_commit(Oid::from_str(base)?)?;
+    repo.checkout_tree(base.as_object(), Some(CheckoutBuilder::new().safe()))?;
+    repo.set_head_detached(base.id())?;
+
+    run_todo(repo_path, &repo, &state_dir, event_sender)
+}
+
+/// Commits the operation the rebase stopped on and applies the rest,
+/// stopping again if another one conflicts.
+pub fn continue_rebase(repo_path: &Path, event_sender: &Sender<ProgramEvent>) -> Result<()> {
+    let repo = repo(repo_path)?;
+
+    let state_dir = todo_dir(&repo);
+    if state_dir.exists() {
+        return continue_todo(repo_path, &repo, &state_dir, event_sender);
+    }
+
+    let mut rebase = open_rebase(&repo)?;
+
+    if rebase.operation_current().is_some() {
+        commit_operation(
+            repo_path,
+            &repo,
+            &mut rebase,
+            "rebase (pick)",
+            None,
+            event_sender,
+        )?;
+    }
+
+    while let Some(operation) = rebase.next() {
+        operation?;
+        commit_operation(
+            repo_path,
+            &repo,
+            &mut rebase,
+            "rebase (pick)",
+            None,
+            event_sender,
+        )?;
+    }
+
+    rebase.finish(Some(&signature(repo_path)?))?;
+
+    Ok(())
+}
+
+pub fn abort_rebase(repo_path: &Path) -> Result<()> {
+    let repo = repo(repo_path)?;
+
+    let state_dir = todo_dir(&repo);
+    if state_dir.exists() {
+        return abort_git_rebase(&repo, &state_dir);
+    }
+
+    match repo.open_rebase(None) {
+        Ok(mut rebase) => rebase.abort()?,
+        Err(_) => {
+            let state_dir = ["rebase-merge", "rebase-apply"]
+                .iter()
+                .map(|dir| repo.path().join(dir))
+                .find(|dir| dir.exists());
+
+            match state_dir {
+                Some(state_dir) => abort_git_rebase(&repo, &state_dir)?,
+                None => return Err(anyhow::Error::msg("No rebase in progress")),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn is_interactive_rebase(repo_path: &Path) -> Result<bool> {
+    Ok(todo_dir(&repo(repo_path)?).exists())
+}
+
+/// Rebases started by git itself can't be resumed by libgit2, and neither
+/// kind needs more than the branch and commit it started from to abort.
+fn abort_git_rebase(repo: &Repository, state_dir: &Path) -> Result<()> {
+    let orig_head = fs::read_to_string(state_dir.join("orig-head"))?;
+    let orig_head = repo.find_commit(Oid::from_str(orig_head.trim())?)?;
+    let head_name = fs::read_to_string(state_dir.join("head-name"))?;
+    let head_name = head_name.trim();
+
+    // A hard reset also cleans up the rebase state
+    repo.reset(orig_head.as_object(), ResetType::Hard, None)?;
+    if head_name.starts_with("refs/") {
+        repo.reference(head_name, orig_head.id(), true, "rebase: aborting")?;
+        repo.set_head(head_name)?;
+    }
+    if state_dir.exists() {
+        fs::remove_dir_all(state_dir)?;
+    }
+
+    Ok(())
+}
+
+fn open_rebase(repo: &Repository) -> Result<Rebase<'_>> {
+    match repo.open_rebase(None) {
+        Ok(rebase) => Ok(rebase),
+        Err(_) => Err(anyhow::Error::msg(
+            "This rebase was started by git, continue it with git rebase --continue",
+        )),
+    }
+}
+
+/// Commits the operation the rebase is on by hand rather than with
+/// `Rebase::commit`, which can't sign commits, so `commit.gpgsign` is
+/// honoured. HEAD is moved to the commit, which is where the next operation
+/// is applied.
+fn commit_operation(
+    repo_path: &Path,
+    repo: &Repository,
+    rebase: &mut Rebase,
+    reflog: &str,
+    message: Option<&str>,
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<()> {
+    let conflicts = get_conflicted_paths(repo_path)?;
+    if !conflicts.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Rebase stopped on conflicts in {}. Resolve them from the conflicts view (m in Files).",
+            conflicts.join(", ")
+        ));
+    }
+
+    let picked = match rebase
+        .operation_current()
+        .and_then(|index| rebase.nth(index))
+    {
+        Some(operation) => repo.find_commit(operation.id())?,
+        None => return Ok(()),
+    };
+
+    let mut index = repo.index()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let head = repo.head()?.peel_to_commit()?;
+
+    let message = match message {
+        Some(message) => message,
+        None => picked.message().unwrap_or(""),
+    };
+
+    if tree.id() == head.tree_id() {
+        let already_committed = head.author().when() == picked.author().when()
+            && head.message().unwrap_or("") == message;
+        if already_committed {
+            return Ok(());
+        }
+
+        // Commits that were empty to begin with are kept, like git does
+        let picked_parent_tree = match picked.parent(0) {
+            Ok(parent) => Some(parent.tree_id()),
+            Err(_) => None,
+        };
+        if picked_parent_tree != Some(picked.tree_id()) {
+            event_sender
+                .send(ProgramEvent::Error(anyhow::anyhow!(
+                    "Dropped {} \"{}\", its changes are already upstream",
+                    &picked.id().to_string()[0..7],
+                    picked.summary().unwrap_or("")
+                )))
+                .expect("Send failed.");
+            return Ok(());
+        }
+    }
+
+    commit_to_head(
+        repo,
+        reflog,
+        &picked.author(),
+        &signature(repo_path)?,
+        message,
+        &tree,
+        &[&head],
+    )?;
+
+    Ok(())
+}
+
+fn todo_dir(repo: &Repository) -> PathBuf {
+    repo.path().join("rebase-buddy")
+}
+
+fn write_todo(state_dir: &Path, todo: &[RebaseTodo]) -> Result<()> {
+    let mut lines = String::new();
+
+    for entry in todo {
+        lines.push_str(&format!("{} {}\n", entry.action.name(), entry.id));
+
+        if let Some(message) = &entry.message {
+            fs::write(state_dir.join(format!("message-{}", entry.id)), message)?;
+        }
+    }
+
+    fs::write(state_dir.join("todo"), lines)?;
+    Ok(())
+}
+
+fn read_todo_line(state_dir: &Path, line: &str) -> Result<RebaseTodo> {
+    let (action, id) = line.split_once(' ').unwrap_or((line, ""));
+
+    let action = match RebaseAction::from_name(action) {
+        Some(action) => action,
+        None => return Err(anyhow::anyhow!("Unknown rebase action {}", action)),
+    };
+
+    Ok(RebaseTodo {
+        action,
+        id: id.to_string(),
+        summary: String::new(),
+        message: fs::read_to_string(state_dir.join(format!("message-{}", id))).ok(),
+    })
+}
+
+fn read_todo(state_dir: &Path) -> Result<Vec<RebaseTodo>> {
+    fs::read_to_string(state_dir.join("todo"))?
+        .lines()
+        .map(|line| read_todo_line(state_dir, line))
+        .collect()
+}
+
+fn continue_todo(
+    repo_path: &Path,
+    repo: &Repository,
+    state_dir: &Path,
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<()> {
+    let current = state_dir.join("current");
+
+    // Stopped on conflicts, so the line hasn't been committed yet
+    if current.exists() {
+        let entry = read_todo_line(state_dir, fs::read_to_string(&current)?.trim())?;
+        let mut rebase = open_rebase(repo)?;
+
+        commit_todo(repo_path, repo, &mut rebase, &entry, event_sender)?;
+        fs::remove_file(&current)?;
+
+        if entry.action == RebaseAction::Edit {
+            return Err(stopped_to_edit(&entry));
+        }
+        rebase.finish(Some(&signature(repo_path)?))?;
+    } else if let Ok(mut rebase) = repo.open_rebase(None) {
+        // Stopped to edit a commit
+        rebase.finish(Some(&signature(repo_path)?))?;
+    }
+
+    run_todo(repo_path, repo, state_dir, event_sender)
+}
+
+fn run_todo(
+    repo_path: &Path,
+    repo: &Repository,
+    state_dir: &Path,
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<()> {
+    loop {
+        let mut todo = read_todo(state_dir)?;
+        if todo.is_empty() {
+            break;
+        }
+
+        let entry = todo.remove(0);
+        write_todo(state_dir, &todo)?;
+
+        if entry.action == RebaseAction::Drop {
+            continue;
+        }
+
+        let commit = repo.find_commit(Oid::from_str(&entry.id)?)?;
+        let branch = repo.find_annotated_commit(commit.id())?;
+        let upstream = repo.find_annotated_commit(commit.parent_id(0)?)?;
+        let onto = repo.find_annotated_commit(repo.head()?.peel_to_commit()?.id())?;
+
+        let mut options = RebaseOptions::new();
+        options.checkout_options(conflict_checkout());
+        let mut rebase = repo.rebase(
+            Some(&branch),
+            Some(&upstream),
+            Some(&onto),
+            Some(&mut options),
+        )?;
+
+        fs::write(
+            state_dir.join("current"),
+            format!("{} {}", entry.action.name(), entry.id),
+        )?;
+        if let Some(operation) = rebase.next() {
+            operation?;
+        }
+
+        let conflicts = get_conflicted_paths(repo_path)?;
+        if !conflicts.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Rebase stopped on conflicts in {} while applying {}. Resolve them from the conflicts view (m in Files).",
+                conflicts.join(", "),
+                &entry.id[0..7]
+            ));
+        }
+
+        commit_todo(repo_path, repo, &mut rebase, &entry, event_sender)?;
+        fs::remove_file(state_dir.join("current"))?;
+
+        // Leave the rebase open so HEAD can be amended before continuing
+        if entry.action == RebaseAction::Edit {
+            return Err(stopped_to_edit(&entry));
+        }
+        rebase.finish(Some(&signature(repo_path)?))?;
+    }
+
+    finish_todo(repo, state_dir)
+}
+
+/// Stopping for an edit is reported the same way as stopping on conflicts,
+/// so the user knows the rebase is waiting for them.
+fn stopped_to_edit(entry: &RebaseTodo) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Rebase stopped to edit {}. Amend it (e in Files), then continue (r in Files).",
+        &entry.id[0..7]
+    )
+}
+
+fn commit_todo(
+    repo_path: &Path,
+    repo: &Repository,
+    rebase: &mut Rebase,
+    entry: &RebaseTodo,
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<()> {
+    let committer = signature(repo_path)?;
+
+    match entry.action {
+        RebaseAction::Squash | RebaseAction::Fixup => {
+            let conflicts = get_conflicted_paths(repo_path)?;
+            if !conflicts.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Resolve the conflicts in {} first",
+                    conflicts.join(", ")
+                ));
+            }
+
+            // Fold the changes into the commit before it
+            let mut index = repo.index()?;
+            let tree = repo.find_tree(index.write_tree()?)?;
+            let head = repo.head()?.peel_to_commit()?;
+            let parents = head.parents().collect::<Vec<_>>();
+
+            let message = match entry.action {
+                RebaseAction::Squash => {
+                    let commit = repo.find_commit(Oid::from_str(&entry.id)?)?;
+                    format!(
+                        "{}\n\n{}",
+                        head.message().unwrap_or("").trim_end(),
+                        commit.message().unwrap_or("").trim_end()
+                    )
+                }
+                _ => head.message().unwrap_or("").to_string(),
+            };
+
+            commit_to_head(
+                repo,
+                &format!("rebase ({})", entry.action.name()),
+                &head.author(),
+                &committer,
+                &message,
+                &tree,
+                &parents.iter().collect::<Vec<_>>(),
+            )?;
+
+            Ok(())
+        }
+        _ => commit_operation(
+            repo_path,
+            repo,
+            rebase,
+            &format!("rebase ({})", entry.action.name()),
+            entry.message.as_deref(),
+            event_sender,
+        ),
+    }
+}
+
+/// Points the branch the rebase started on at the rewritten commits.
+fn finish_todo(repo: &Repository, state_dir: &Path) -> Result<()> {
+    let head_name = fs::read_to_string(state_dir.join("head-name"))?;
+    let head_name = head_name.trim();
+
+    if head_name.starts_with("refs/") {
+        let head = repo.head()?.peel_to_commit()?.id();
+        repo.reference(head_name, head, true, "rebase -i (finish)")?;
+        repo.set_head(head_name)?;
+    }
+    fs::remove_dir_all(state_dir)?;
+
+    Ok(())
+}
@@ -0,0 +1,426 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use crossbeam::channel::{bounded, Receiver, Sender, TryRecvError};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use git2::{Diff, Repository};
+use regex::Regex;
+
+use crate::app::{GitEvent, ProgramEvent};
+use crate::git::log::{decorated_commit, history, ref_map, LogScope, PathFilter};
+use crate::git::repo;
+use crate::git::time::{
+    SECONDS_IN_DAY, SECONDS_IN_HOUR, SECONDS_IN_MONTH, SECONDS_IN_WEEK, SECONDS_IN_YEAR,
+};
+
+/// How many commits are checked between sending results.
+const BATCH_SIZE: usize = 100;
+
+const QUALIFIERS: [&str; 7] = ["author:", "hash:", "body:", "since:", "until:", "-S", "-G"];
+
+/// A condition a commit has to meet, beyond fuzzy matching its summary.
+#[derive(Debug)]
+enum Filter {
+    /// Part of the author's name or email.
+    Author(String),
+    /// The start of the commit id.
+    Hash(String),
+    /// Part of the whole message.
+    Body(String),
+    /// Committed at or after this unix time.
+    Since(i64),
+    /// Committed before this unix time.
+    Until(i64),
+    /// Changes how many times the string appears, like `git log -S`.
+    Pickaxe(String),
+    /// Adds or removes a line matching the regex, like `git log -G`.
+    DiffRegex(Regex),
+}
+
+/// A log search: words fuzzy matched against the summary, narrowed down by
+/// qualifiers such as `author:jane` or `-Sneedle`. Values with spaces are
+/// quoted, `author:"Jane Doe"`.
+#[derive(Debug)]
+pub struct LogQuery {
+    text: String,
+    filters: Vec<Filter>,
+}
+
+impl LogQuery {
+    pub fn parse(query: &str) -> Result<Self> {
+        Self::parse_at(query, chrono::Utc::now().timestamp())
+    }
+
+    fn parse_at(query: &str, now: i64) -> Result<Self> {
+        let mut words = Vec::new();
+        let mut filters = Vec::new();
+
+        for token in tokens(query) {
+            let filter = match qualifier(&token) {
+                Some((qualifier, "")) => return Err(anyhow!("{} needs a value", qualifier)),
+                Some(("author:", value)) => Filter::Author(value.to_lowercase()),
+                Some(("hash:", value)) => Filter::Hash(value.to_lowercase()),
+                Some(("body:", value)) => Filter::Body(value.to_lowercase()),
+                Some(("since:", value)) => Filter::Since(parse_date(value, now)?),
+                Some(("until:", value)) => {
+                    Filter::Until(parse_date(value, now)? + until_offset(value))
+                }
+                Some(("-S", value)) => Filter::Pickaxe(value.to_string()),
+                Some((_, value)) => Filter::DiffRegex(
+                    Regex::new(value).map_err(|err| anyhow!("Invalid -G regex: {}", err))?,
+                ),
+                None => {
+                    words.push(token);
+                    continue;
+                }
+            };
+            filters.push(filter);
+        }
+
+        Ok(Self {
+            text: words.join(" "),
+            filters,
+        })
+    }
+
+    fn matches(
+        &self,
+        repo: &Repository,
+        commit: &git2::Commit,
+        matcher: &SkimMatcherV2,
+    ) -> Result<bool> {
+        if !self.text.is_empty()
+            && matcher
+                .fuzzy_match(commit.summary().unwrap_or(""), &self.text)
+                .is_none()
+        {
+            return Ok(false);
+        }
+
+        // The history is only diffed once the cheap checks pass
+        let mut diff = None;
+        for filter in &self.filters {
+            let matched = match filter {
+                Filter::Author(author) => {
+                    let signature = commit.author();
+                    [signature.name(), signature.email()]
+                        .iter()
+                        .flatten()
+                        .any(|part| part.to_lowercase().contains(author))
+                }
+                Filter::Hash(hash) => commit.id().to_string().starts_with(hash),
+                Filter::Body(body) => commit.message().unwrap_or("").to_lowercase().contains(body),
+                Filter::Since(since) => commit.time().seconds() >= *since,
+                Filter::Until(until) => commit.time().seconds() < *until,
+                Filter::Pickaxe(_) | Filter::DiffRegex(_) => continue,
+            };
+            if !matched {
+                return Ok(false);
+            }
+        }
+
+        for filter in &self.filters {
+            let matched = match filter {
+                Filter::Pickaxe(needle) => {
+                    pickaxe(repo, cached_diff(repo, commit, &mut diff)?, needle)?
+                }
+                Filter::DiffRegex(regex) => {
+                    diff_regex(cached_diff(repo, commit, &mut diff)?, regex)?
+                }
+                _ => continue,
+            };
+            if !matched {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Whether a search has qualifiers, which are searched for in the
+/// background instead of among the commits loaded.
+pub fn has_qualifiers(query: &str) -> bool {
+    tokens(query).iter().any(|token| qualifier(token).is_some())
+}
+
+/// Searches the whole history of a scope, or of a path in it, in the
+/// background. Matching commits come back in batches as
+/// `GitEvent::SearchResults` tagged with `generation`. The search stops early
+/// when the returned channel is dropped.
+pub fn search_commits(
+    repo_path: PathBuf,
+    scope: LogScope,
+    paths: Option<PathFilter>,
+    query: LogQuery,
+    generation: usize,
+    event_sender: Sender<ProgramEvent>,
+) -> Sender<()> {
+    let (cancel_sender, cancel_receiver) = bounded(0);
+
+    thread::spawn(move || {
+        let result = send_results(
+            &repo_path,
+            scope,
+            paths,
+            &query,
+            generation,
+            &cancel_receiver,
+            &event_sender,
+        );
+        if let Err(err) = result {
+            event_sender
+                .send(ProgramEvent::Git(GitEvent::SearchResults(
+                    generation,
+                    Vec::new(),
+                    true,
+                )))
+                .expect("Send failed.");
+            event_sender
+                .send(ProgramEvent::Error(err))
+                .expect("Send failed.");
+        }
+    });
+
+    cancel_sender
+}
+
+fn send_results(
+    repo_path: &Path,
+    scope: LogScope,
+    mut paths: Option<PathFilter>,
+    query: &LogQuery,
+    generation: usize,
+    cancel: &Receiver<()>,
+    event_sender: &Sender<ProgramEvent>,
+) -> Result<()> {
+    let repo = repo(repo_path)?;
+    let mut refs = ref_map(&repo)?;
+    let matcher = SkimMatcherV2::default();
+    let mut oids = history(&repo, scope)?.flatten().peekable();
+
+    loop {
+        if cancel.try_recv() == Err(TryRecvError::Disconnected) {
+            return Ok(());
+        }
+
+        let mut commits = Vec::new();
+        for oid in oids.by_ref().take(BATCH_SIZE) {
+            let commit = repo.find_commit(oid)?;
+            // Every commit goes past the path filter, to follow renames
+            if let Some(paths) = &mut paths {
+                if !paths.touches(&repo, &commit)? {
+                    continue;
+                }
+            }
+            if query.matches(&repo, &commit, &matcher)? {
+                commits.push(decorated_commit(&repo, oid, &mut refs)?);
+            }
+        }
+
+        let finished = oids.peek().is_none();
+        if !commits.is_empty() || finished {
+            event_sender
+                .send(ProgramEvent::Git(GitEvent::SearchResults(
+                    generation, commits, finished,
+                )))
+                .expect("Send failed.");
+        }
+        if finished {
+            return Ok(());
+        }
+    }
+}
+
+/// The changes of a commit against its first parent, diffed on first use.
+/// Merges have no changes of their own, as with `git log -S`.
+fn cached_diff<'a, 'r>(
+    repo: &'r Repository,
+    commit: &git2::Commit,
+    diff: &'a mut Option<Option<Diff<'r>>>,
+) -> Result<Option<&'a Diff<'r>>> {
+    if diff.is_none() {
+        *diff = Some(match commit.parent_count() {
+            0 => Some(repo.diff_tree_to_tree(None, Some(&commit.tree()?), None)?),
+            1 => Some(repo.diff_tree_to_tree(
+                Some(&commit.parent(0)?.tree()?),
+                Some(&commit.tree()?),
+                None,
+            )?),
+            _ => None,
+        });
+    }
+
+    Ok(diff.as_ref().and_then(|diff| diff.as_ref()))
+}
+
+fn pickaxe(repo: &Repository, diff: Option<&Diff>, needle: &str) -> Result<bool> {
+    let diff = match diff {
+        Some(diff) => diff,
+        None => return Ok(false),
+    };
+
+    let count = |oid: git2::Oid| -> Result<usize> {
+        if oid.is_zero() {
+            return Ok(0);
+        }
+        let blob = repo.find_blob(oid)?;
+        match blob.is_binary() {
+            true => Ok(0),
+            false => Ok(String::from_utf8_lossy(blob.content())
+                .matches(needle)
+                .count()),
+        }
+    };
+
+    for delta in diff.deltas() {
+        if count(delta.old_file().id())? != count(delta.new_file().id())? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn diff_regex(diff: Option<&Diff>, regex: &Regex) -> Result<bool> {
+    let diff = match diff {
+        Some(diff) => diff,
+        None => return Ok(false),
+    };
+
+    let mut found = false;
+    let result = diff.foreach(
+        &mut |_, _| true,
+        None,
+        None,
+        Some(&mut |_, _, line| {
+            if matches!(line.origin(), '+' | '-')
+                && regex.is_match(&String::from_utf8_lossy(line.content()))
+            {
+                found = true;
+            }
+            // Stops the walk at the first match
+            !found
+        }),
+    );
+
+    match result {
+        Err(_) if found => Ok(true),
+        Err(err) => Err(err.into()),
+        Ok(()) => Ok(found),
+    }
+}
+
+/// Splits a query on whitespace, keeping quoted parts together.
+fn tokens(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+fn qualifier(token: &str) -> Option<(&'static str, &str)> {
+    QUALIFIERS.iter().find_map(|qualifier| {
+        token
+            .strip_prefix(qualifier)
+            .map(|value| (*qualifier, value))
+    })
+}
+
+/// A date as `YYYY-MM-DD`, taken as midnight UTC like the dates shown, or
+/// an age such as `3d` counted back from now, in h, d, w, m or y.
+fn parse_date(value: &str, now: i64) -> Result<i64> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let midnight = date.and_hms_opt(0, 0, 0).expect("midnight exists");
+        return Ok(midnight.timestamp());
+    }
+
+    let invalid = || anyhow!("{} isn't a date, use YYYY-MM-DD or an age like 2w", value);
+    let unit = match value.chars().last().ok_or_else(invalid)? {
+        'h' => SECONDS_IN_HOUR,
+        'd' => SECONDS_IN_DAY,
+        'w' => SECONDS_IN_WEEK,
+        'm' => SECONDS_IN_MONTH,
+        'y' => SECONDS_IN_YEAR,
+        _ => return Err(invalid()),
+    };
+    let count: u64 = value[..value.len() - 1].parse().map_err(|_| invalid())?;
+
+    // Ages too large to go back that far aren't dates either
+    count
+        .checked_mul(unit)
+        .and_then(|age| i64::try_from(age).ok())
+        .and_then(|age| now.checked_sub(age))
+        .ok_or_else(invalid)
+}
+
+/// `until:` a date includes the whole of that day.
+fn until_offset(value: &str) -> i64 {
+    match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(_) => SECONDS_IN_DAY as i64,
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn splits_words_and_qualifiers() {
+        let query = LogQuery::parse_at(r#"fix parser author:"Jane Doe" -Sneedle"#, 0).unwrap();
+
+        assert_eq!(query.text, "fix parser");
+        assert!(matches!(&query.filters[0], Filter::Author(author) if author == "jane doe"));
+        assert!(matches!(&query.filters[1], Filter::Pickaxe(needle) if needle == "needle"));
+    }
+
+    #[test]
+    fn plain_text_has_no_qualifiers() {
+        assert!(!has_qualifiers("fix the author bug"));
+        assert!(has_qualifiers("fix hash:abc"));
+    }
+
+    #[test]
+    fn parses_dates_and_ages() {
+        let now = 1_000_000_000;
+
+        assert_eq!(parse_date("2001-09-09", now).unwrap(), 999_993_600);
+        assert_eq!(parse_date("2d", now).unwrap(), now - 172_800);
+        assert!(parse_date("tuesday", now).is_err());
+        assert!(parse_date("d", now).is_err());
+    }
+
+    #[test]
+    fn until_includes_the_day() {
+        let query = LogQuery::parse_at("until:2001-09-09", 0).unwrap();
+
+        assert!(matches!(query.filters[0], Filter::Until(1_000_080_000)));
+    }
+
+    #[test]
+    fn rejects_empty_and_invalid_values() {
+        assert!(LogQuery::parse_at("author:", 0).is_err());
+        assert!(LogQuery::parse_at("-G(", 0).is_err());
+        assert!(LogQuery::parse_at("since:99999999999999y", 0).is_err());
+    }
+}
@@ -0,0 +1,405 @@
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use git2::{Config, ErrorCode, Oid, Repository, Signature};
+
+use crate::git::repo;
+
+/// Whether a commit is signed and what checking the signature found, like
+/// git's `%G?`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SignatureStatus {
+    Unsigned,
+    /// Signed, but not verified yet.
+    Unchecked,
+    /// A good signature, from the signer named.
+    Good(String),
+    Bad,
+    /// A good signature that has expired, from the signer named.
+    Expired(String),
+    /// A good signature made with a key that has since expired.
+    ExpiredKey(String),
+    /// A good signature made with a key that has since been revoked.
+    Revoked(String),
+    /// Signed with a key that isn't known or trusted here.
+    Unknown,
+    /// The signature couldn't be checked, for the reason given.
+    Error(String),
+}
+
+impl SignatureStatus {
+    pub fn description(&self) -> String {
+        match self {
+            SignatureStatus::Unsigned => "Not signed".to_string(),
+            SignatureStatus::Unchecked => "Checking the signature".to_string(),
+            SignatureStatus::Good(signer) => format!("Good signature from {}", signer),
+            SignatureStatus::Bad => "Bad signature".to_string(),
+            SignatureStatus::Expired(signer) => {
+                format!("Expired signature from {}", signer)
+            }
+            SignatureStatus::ExpiredKey(signer) => {
+                format!("Good signature from {}, but the key has expired", signer)
+            }
+            SignatureStatus::Revoked(signer) => {
+                format!("Good signature from {}, but the key was revoked", signer)
+            }
+            SignatureStatus::Unknown => "Signed with an unknown or untrusted key".to_string(),
+            SignatureStatus::Error(reason) => format!("Couldn't check the signature: {}", reason),
+        }
+    }
+}
+
+/// Signs a commit buffer when `commit.gpgsign` is set, using the program
+/// picked by `gpg.format`. Returns `None` when commits aren't signed.
+pub fn sign_commit(
+    repo: &Repository,
+    buffer: &str,
+    committer: &Signature,
+) -> Result<Option<String>> {
+    let config = repo.config()?;
+
+    if !config.get_bool("commit.gpgsign").unwrap_or(false) {
+        return Ok(None);
+    }
+
+    let key = config.get_string("user.signingkey").ok();
+    let format = config
+        .get_string("gpg.format")
+        .unwrap_or_else(|_| "openpgp".to_string());
+
+    let signature = match format.as_str() {
+        "openpgp" | "x509" => gpg_sign(&config, &format, key, committer, buffer)?,
+        "ssh" => ssh_sign(&config, key, buffer)?,
+        format => return Err(anyhow::anyhow!("Unknown gpg.format {}", format)),
+    };
+
+    Ok(Some(signature))
+}
+
+fn gpg_sign(
+    config: &Config,
+    format: &str,
+    key: Option<String>,
+    committer: &Signature,
+    buffer: &str,
+) -> Result<String> {
+    let program = gpg_program(config, format);
+
+    // Without a key gpg picks one matching the committer, the same as git
+    let key = key.unwrap_or_else(|| {
+        format!(
+            "{} <{}>",
+            committer.name().unwrap_or(""),
+            committer.email().unwrap_or("")
+        )
+    });
+
+    run_signer(&program, &["--status-fd=2", "-bsau", &key], buffer)
+}
+
+/// `gpg.<format>.program`, falling back to `gpg.program` for openpgp.
+fn gpg_program(config: &Config, format: &str) -> String {
+    config
+        .get_string(&format!("gpg.{}.program", format))
+        .or_else(|err| match format {
+            "openpgp" => config.get_string("gpg.program"),
+            _ => Err(err),
+        })
+        .unwrap_or_else(|_| match format {
+            "x509" => "gpgsm".to_string(),
+            _ => "gpg".to_string(),
+        })
+}
+
+fn ssh_sign(config: &Config, key: Option<String>, buffer: &str) -> Result<String> {
+    let program = config
+        .get_string("gpg.ssh.program")
+        .unwrap_or_else(|_| "ssh-keygen".to_string());
+    let key = key.ok_or_else(|| {
+        anyhow::Error::msg("Set user.signingkey to the key to sign commits with ssh")
+    })?;
+
+    // A literal public key is signed with through the ssh agent
+    let literal = key
+        .strip_prefix("key::")
+        .or_else(|| key.starts_with("ssh-").then_some(key.as_str()));
+
+    match literal {
+        Some(public_key) => {
+            let key_file = create_temp_file("buddy-signing-key", ".pub", public_key)?;
+
+            let key_path = key_file.to_string_lossy().to_string();
+            let signature = run_signer(
+                &program,
+                &["-Y", "sign", "-n", "git", "-f", &key_path, "-U"],
+                buffer,
+            );
+            std::fs::remove_file(&key_file)?;
+            signature
+        }
+        None => {
+            let key_path = expand_home(&key).to_string_lossy().to_string();
+            run_signer(
+                &program,
+                &["-Y", "sign", "-n", "git", "-f", &key_path],
+                buffer,
+            )
+        }
+    }
+}
+
+/// Writes `contents` to a new file in the temp directory for a signing
+/// program to read. The file is only ever created, never opened, so a file
+/// or symlink planted at its name can't be written through.
+fn create_temp_file(prefix: &str, suffix: &str, contents: &str) -> Result<PathBuf> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+
+    for attempt in 0..100 {
+        let path = std::env::temp_dir().join(format!(
+            "{}-{}-{}-{}{}",
+            prefix,
+            std::process::id(),
+            nanos,
+            attempt,
+            suffix
+        ));
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        match options.open(&path) {
+            Ok(mut file) => {
+                file.write_all(contents.as_bytes())?;
+                return Ok(path);
+            }
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Err(anyhow::Error::msg(
+        "Couldn't create a temporary file for the signature",
+    ))
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Pipes the buffer through a signing program, returning the signature it
+/// writes to stdout.
+fn run_signer(program: &str, args: &[&str], buffer: &str) -> Result<String> {
+    let output = run_with_input(program, args, buffer.as_bytes())?;
+    let signature = String::from_utf8_lossy(&output.stdout).to_string();
+
+    if !output.status.success() || signature.trim().is_empty() {
+        return Err(anyhow::anyhow!(
+            "Signing the commit with {} failed:\n{}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        ));
+    }
+
+    Ok(signature)
+}
+
+/// Verifies a commit's signature with gpg, gpgsm or ssh-keygen, whichever
+/// made it. SSH signatures are checked against `gpg.ssh.allowedSignersFile`.
+pub fn verify_commit(repo_path: &Path, id: &str) -> Result<SignatureStatus> {
+    let repo = repo(repo_path)?;
+    let (signature, data) = match repo.extract_signature(&Oid::from_str(id)?, None) {
+        Ok(extracted) => extracted,
+        Err(err) if err.code() == ErrorCode::NotFound => return Ok(SignatureStatus::Unsigned),
+        Err(err) => return Err(err.into()),
+    };
+    let signature = signature
+        .as_str()
+        .ok_or_else(|| anyhow::Error::msg("The signature isn't valid UTF-8"))?;
+
+    let config = repo.config()?;
+    let signature_file = create_temp_file("buddy-signature", "", signature)?;
+
+    let status = match signature {
+        s if s.starts_with("-----BEGIN SSH SIGNATURE-----") => {
+            ssh_verify(&config, &signature_file, &data)
+        }
+        s if s.starts_with("-----BEGIN SIGNED MESSAGE-----") => {
+            gpg_verify(&config, "x509", &signature_file, &data)
+        }
+        _ => gpg_verify(&config, "openpgp", &signature_file, &data),
+    };
+    std::fs::remove_file(&signature_file)?;
+
+    status
+}
+
+fn gpg_verify(
+    config: &Config,
+    format: &str,
+    signature_file: &Path,
+    data: &[u8],
+) -> Result<SignatureStatus> {
+    let program = gpg_program(config, format);
+    let signature_path = signature_file.to_string_lossy().to_string();
+    let output = run_with_input(
+        &program,
+        &["--status-fd=1", "--verify", &signature_path, "-"],
+        data,
+    )?;
+    let status = String::from_utf8_lossy(&output.stdout);
+
+    // Which of these a status line starts with decides the result, the
+    // trust lines only matter for a good signature
+    let mut result = None;
+    let mut trusted = true;
+    for line in status.lines() {
+        let line = match line.strip_prefix("[GNUPG:] ") {
+            Some(line) => line,
+            None => continue,
+        };
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        // The key id comes before the signer's name
+        let signer = rest
+            .split_once(' ')
+            .map_or(rest, |(_, name)| name)
+            .to_string();
+
+        match keyword {
+            "GOODSIG" => result = Some(SignatureStatus::Good(signer)),
+            "EXPSIG" => result = Some(SignatureStatus::Expired(signer)),
+            "EXPKEYSIG" => result = Some(SignatureStatus::ExpiredKey(signer)),
+            "REVKEYSIG" => result = Some(SignatureStatus::Revoked(signer)),
+            "BADSIG" => return Ok(SignatureStatus::Bad),
+            "NO_PUBKEY" => return Ok(SignatureStatus::Unknown),
+            "ERRSIG" => result = Some(SignatureStatus::Error(format!("{} failed", program))),
+            "TRUST_UNDEFINED" | "TRUST_NEVER" => trusted = false,
+            _ => {}
+        }
+    }
+
+    match result {
+        Some(SignatureStatus::Good(_)) if !trusted => Ok(SignatureStatus::Unknown),
+        Some(result) => Ok(result),
+        None => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = stderr.lines().last().unwrap_or("").trim();
+            match reason.is_empty() {
+                true => Ok(SignatureStatus::Error(format!("{} failed", program))),
+                false => Ok(SignatureStatus::Error(reason.to_string())),
+            }
+        }
+    }
+}
+
+fn ssh_verify(config: &Config, signature_file: &Path, data: &[u8]) -> Result<SignatureStatus> {
+    let program = config
+        .get_string("gpg.ssh.program")
+        .unwrap_or_else(|_| "ssh-keygen".to_string());
+    let signature_path = signature_file.to_string_lossy().to_string();
+
+    let allowed_signers = match config.get_path("gpg.ssh.allowedSignersFile") {
+        Ok(path) => path.to_string_lossy().to_string(),
+        Err(_) => return ssh_check_novalidate(&program, &signature_path, data),
+    };
+
+    let principals = run_with_input(
+        &program,
+        &[
+            "-Y",
+            "find-principals",
+            "-f",
+            &allowed_signers,
+            "-s",
+            &signature_path,
+        ],
+        &[],
+    )?;
+    let principal = String::from_utf8_lossy(&principals.stdout)
+        .lines()
+        .next()
+        .unwrap_or("")
+        .to_string();
+
+    if !principals.status.success() || principal.is_empty() {
+        return ssh_check_novalidate(&program, &signature_path, data);
+    }
+
+    let output = run_with_input(
+        &program,
+        &[
+            "-Y",
+            "verify",
+            "-f",
+            &allowed_signers,
+            "-I",
+            &principal,
+            "-n",
+            "git",
+            "-s",
+            &signature_path,
+        ],
+        data,
+    )?;
+
+    match output.status.success() {
+        true => Ok(SignatureStatus::Good(principal)),
+        false => Ok(SignatureStatus::Bad),
+    }
+}
+
+/// Checks an ssh signature is intact without knowing who should have made it.
+fn ssh_check_novalidate(
+    program: &str,
+    signature_path: &str,
+    data: &[u8],
+) -> Result<SignatureStatus> {
+    let output = run_with_input(
+        program,
+        &["-Y", "check-novalidate", "-n", "git", "-s", signature_path],
+        data,
+    )?;
+
+    match output.status.success() {
+        true => Ok(SignatureStatus::Unknown),
+        false => Ok(SignatureStatus::Bad),
+    }
+}
+
+fn run_with_input(program: &str, args: &[&str], input: &[u8]) -> Result<Output> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| anyhow::anyhow!("Couldn't run {}: {}", program, err))?;
+
+    // Written from another thread, so a child filling its stderr before
+    // reading all of stdin can't block both ends
+    let stdin = child.stdin.take();
+    thread::scope(|scope| {
+        let writer = scope.spawn(move || match stdin {
+            Some(mut stdin) => stdin.write_all(input),
+            None => Ok(()),
+        });
+        let output = child.wait_with_output()?;
+
+        match writer.join().expect("Stdin writer panicked.") {
+            // The child quit early, its output says why
+            Err(err) if err.kind() != ErrorKind::BrokenPipe => Err(err.into()),
+            _ => Ok(output),
+        }
+    })
+}
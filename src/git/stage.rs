@@ -1,7 +1,9 @@
 use std::path::Path;
 
 use anyhow::Result;
+use git2::{ApplyLocation, ApplyOptions, Diff, DiffOptions, Repository};
 
+use crate::git::diff::HunkHeader;
 use crate::git::repo;
 
 pub fn stage_file(repo_path: &Path, file_path: &str) -> Result<()> {
@@ -21,7 +23,7 @@ pub fn unstage_file(repo_path: &Path, file_path: &str) -> Result<()> {
 
     if let Some(head) = repo.head()?.target() {
         let obj = repo.find_object(head, Some(git2::ObjectType::Commit))?;
-        repo.reset_default(Some(&obj), &[path])?;
+        repo.reset_default(Some(&obj), [path])?;
     }
     Ok(())
 }
@@ -43,3 +45,65 @@ pub fn unstage_all(repo_path: &Path) -> Result<()> {
     }
     Ok(())
 }
+
+/// Applies a patch written by `git::patch::build_patch` to the index.
+pub fn apply_patch(repo_path: &Path, patch: &str) -> Result<()> {
+    let repo = repo(repo_path)?;
+    let diff = Diff::from_buffer(patch.as_bytes())?;
+
+    repo.apply(&diff, ApplyLocation::Index, None)?;
+
+    Ok(())
+}
+
+pub fn stage_hunk(repo_path: &Path, file_path: &str, hunk: HunkHeader) -> Result<()> {
+    let repo = repo(repo_path)?;
+    let mut opt = file_diff_options(file_path);
+    let diff = repo.diff_index_to_workdir(None, Some(&mut opt))?;
+
+    apply_hunk(&repo, &diff, hunk, ApplyLocation::Index)
+}
+
+pub fn unstage_hunk(repo_path: &Path, file_path: &str, hunk: HunkHeader) -> Result<()> {
+    let repo = repo(repo_path)?;
+    let tree = repo.head()?.peel_to_tree()?;
+
+    // Applying the reversed staged diff to the index takes the hunk back out.
+    let mut opt = file_diff_options(file_path);
+    opt.reverse(true);
+    let diff = repo.diff_tree_to_index(Some(&tree), None, Some(&mut opt))?;
+
+    apply_hunk(&repo, &diff, hunk.reversed(), ApplyLocation::Index)
+}
+
+pub fn file_diff_options(file_path: &str) -> DiffOptions {
+    let mut opt = DiffOptions::new();
+    opt.pathspec(file_path).disable_pathspec_match(true);
+    opt
+}
+
+pub fn apply_hunk(
+    repo: &Repository,
+    diff: &Diff,
+    hunk: HunkHeader,
+    location: ApplyLocation,
+) -> Result<()> {
+    let mut found = false;
+    let mut options = ApplyOptions::new();
+    options.hunk_callback(|h| match h {
+        Some(h) if HunkHeader::from(&h) == hunk => {
+            found = true;
+            true
+        }
+        _ => false,
+    });
+
+    repo.apply(diff, location, Some(&mut options))?;
+    drop(options);
+
+    if !found {
+        return Err(anyhow::Error::msg("Hunk no longer matches the diff"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use std::path::Path;
+
+use anyhow::Result;
+use git2::{ApplyLocation, DiffOptions, Oid, StashFlags};
+
+use crate::git::diff::{diff_lines, DiffLine};
+use crate::git::identity::signature;
+use crate::git::repo;
+use crate::git::status::get_conflicted_paths;
+use crate::git::time::CommitDate;
+
+#[derive(Clone, Debug)]
+pub struct Stash {
+    pub index: usize,
+    pub id: String,
+    pub message: String,
+    pub time: CommitDate,
+}
+
+pub fn get_stashes(repo_path: &Path) -> Result<Vec<Stash>> {
+    let mut repo = repo(repo_path)?;
+    let mut entries = Vec::new();
+
+    repo.stash_foreach(|index, message, oid| {
+        entries.push((index, message.to_string(), *oid));
+        true
+    })?;
+
+    let mut stashes = Vec::new();
+    for (index, message, oid) in entries {
+        let commit = repo.find_commit(oid)?;
+
+        stashes.push(Stash {
+            index,
+            id: oid.to_string(),
+            message,
+            time: CommitDate::new(commit.time()),
+        });
+    }
+
+    Ok(stashes)
+}
+
+pub fn stash_save(repo_path: &Path, include_untracked: bool) -> Result<()> {
+    let mut repo = repo(repo_path)?;
+    let signature = signature(repo_path)?;
+
+    let flags = if include_untracked {
+        StashFlags::INCLUDE_UNTRACKED
+    } else {
+        StashFlags::DEFAULT
+    };
+
+    repo.stash_save2(&signature, None, Some(flags))?;
+
+    Ok(())
+}
+
+/// Stashes only what is staged, like `git stash --staged`, leaving unstaged
+/// changes in the working tree. libgit2 has no flag for this so the stash
+/// commits are written by hand.
+pub fn stash_staged(repo_path: &Path) -> Result<()> {
+    let repo = repo(repo_path)?;
+    let signature = signature(repo_path)?;
+
+    let head = repo.head()?;
+    let branch = head.shorthand().unwrap_or("HEAD").to_string();
+    let head = head.peel_to_commit()?;
+    let head_tree = head.tree()?;
+
+    let mut index = repo.index()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+
+    if tree.id() == head_tree.id() {
+        return Err(anyhow::Error::msg("No staged changes to stash"));
+    }
+
+    let summary = format!(
+        "{}: {} {}",
+        branch,
+        &head.id().to_string()[0..7],
+        head.summary().unwrap_or("")
+    );
+    let message = format!("WIP on {}", summary);
+
+    let index_commit = repo.commit(
+        None,
+        &signature,
+        &signature,
+        &format!("index on {}", summary),
+        &tree,
+        &[&head],
+    )?;
+    let index_commit = repo.find_commit(index_commit)?;
+    let stash_commit = repo.commit(
+        None,
+        &signature,
+        &signature,
+        &message,
+        &tree,
+        &[&head, &index_commit],
+    )?;
+
+    // Take the staged changes out of the working tree first, so nothing has
+    // been touched yet if they overlap with unstaged ones.
+    let mut opt = DiffOptions::new();
+    opt.reverse(true);
+    let diff = repo.diff_tree_to_index(Some(&head_tree), Some(&index), Some(&mut opt))?;
+    if repo.apply(&diff, ApplyLocation::WorkDir, None).is_err() {
+        return Err(anyhow::Error::msg(
+            "Staged changes overlap unstaged changes, stash them together instead",
+        ));
+    }
+
+    repo.reference_ensure_log("refs/stash")?;
+    repo.reference("refs/stash", stash_commit, true, &message)?;
+
+    index.read_tree(&head_tree)?;
+    index.write()?;
+
+    Ok(())
+}
+
+pub fn stash_apply(repo_path: &Path, index: usize) -> Result<()> {
+    let mut repo = repo(repo_path)?;
+    repo.stash_apply(index, None)?;
+    Ok(())
+}
+
+/// Applies and drops a stash. Unlike libgit2's `stash_pop` the stash is
+/// kept when applying it leaves conflicts, the same as `git stash pop`.
+pub fn stash_pop(repo_path: &Path, index: usize) -> Result<()> {
+    let mut repo = repo(repo_path)?;
+    repo.stash_apply(index, None)?;
+
+    let conflicts = get_conflicted_paths(repo_path)?;
+    if !conflicts.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Applying stash@{{{}}} conflicted in {}. The stash was kept.",
+            index,
+            conflicts.join(", ")
+        ));
+    }
+
+    repo.stash_drop(index)?;
+    Ok(())
+}
+
+pub fn stash_drop(repo_path: &Path, index: usize) -> Result<()> {
+    let mut repo = repo(repo_path)?;
+    repo.stash_drop(index)?;
+    Ok(())
+}
+
+/// The changes recorded in a stash, including any untracked files it holds.
+pub fn get_stash_diff(repo_path: &Path, id: &str) -> Result<Vec<DiffLine>> {
+    let repo = repo(repo_path)?;
+    let commit = repo.find_commit(Oid::from_str(id)?)?;
+    let base = commit.parent(0)?.tree()?;
+
+    let diff = repo.diff_tree_to_tree(Some(&base), Some(&commit.tree()?), None)?;
+    let mut lines = diff_lines(&diff)?;
+
+    // Untracked files are stored as a third parent with no history
+    if let Ok(untracked) = commit.parent(2) {
+        let diff = repo.diff_tree_to_tree(None, Some(&untracked.tree()?), None)?;
+        lines.append(&mut diff_lines(&diff)?);
+    }
+
+    Ok(lines)
+}
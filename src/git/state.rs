@@ -0,0 +1,225 @@
+use std::path::Path;
+
+use anyhow::Result;
+use crossbeam::channel::Sender;
+use git2::build::CheckoutBuilder;
+use git2::{RepositoryState, Status, StatusOptions};
+
+use crate::app::ProgramEvent;
+use crate::git::commit::{commit, commit_to_head, run_commit_hooks, run_post_commit_hook};
+use crate::git::identity::signature;
+use crate::git::rebase::{abort_rebase, continue_rebase, is_interactive_rebase};
+use crate::git::repo;
+use crate::git::status::get_conflicted_paths;
+
+/// The label shown for an operation in progress, or `None` when the
+/// repository is clean.
+pub fn get_state_label(repo_path: &Path) -> Result<Option<&'static str>> {
+    // Between the steps of an interactive rebase git itself sees no rebase
+    if is_interactive_rebase(repo_path)? {
+        return Ok(Some("REBASING"));
+    }
+
+    let label = match repo(repo_path)?.state() {
+        RepositoryState::Clean => None,
+        RepositoryState::Merge => Some("MERGING"),
+        RepositoryState::Revert | RepositoryState::RevertSequence => Some("REVERTING"),
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => Some("CHERRY-PICKING"),
+        RepositoryState::Bisect => Some("BISECTING"),
+        RepositoryState::Rebase
+        | RepositoryState::RebaseInteractive
+        | RepositoryState::RebaseMerge => Some("REBASING"),
+        RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => Some("AM"),
+    };
+
+    Ok(label)
+}
+
+/// Refuses to start another operation on top of a half-finished one.
+pub fn ensure_clean_state(repo_path: &Path) -> Result<()> {
+    match get_state_label(repo_path)? {
+        Some(state) => Err(anyhow::anyhow!(
+            "The repository is {}, continue or abort that first",
+            state
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Finishes a merge, cherry-pick, revert or rebase that stopped part way,
+/// once its conflicts have all been resolved.
+pub fn continue_operation(repo_path: &Path, event_sender: &Sender<ProgramEvent>) -> Result<()> {
+    let mut repo = repo(repo_path)?;
+
+    if is_rebase(repo.state()) || is_interactive_rebase(repo_path)? {
+        return continue_rebase(repo_path, event_sender);
+    }
+
+    let conflicts = get_conflicted_paths(repo_path)?;
+    if !conflicts.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Resolve the conflicts in {} first",
+            conflicts.join(", ")
+        ));
+    }
+
+    // MERGE_MSG lists the conflicted files as comments
+    let message = match repo.message() {
+        Ok(message) => git2::message_prettify(message, Some(b'#'))?,
+        Err(_) => String::new(),
+    };
+
+    match repo.state() {
+        RepositoryState::Merge => {
+            let mut heads = Vec::new();
+            repo.mergehead_foreach(|oid| {
+                heads.push(*oid);
+                true
+            })?;
+
+            match heads.as_slice() {
+                [head] => commit(repo_path, &message, Some(*head), None, event_sender)?,
+                _ => return Err(anyhow::Error::msg("Octopus merges are not supported")),
+            }
+        }
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+            let picked = repo.find_reference("CHERRY_PICK_HEAD")?.peel_to_commit()?;
+            let message = match message.is_empty() {
+                true => picked.message().unwrap_or("").to_string(),
+                false => message,
+            };
+            let message = run_commit_hooks(repo_path, &message, event_sender)?;
+
+            let mut index = repo.index()?;
+            let tree = repo.find_tree(index.write_tree()?)?;
+            let head = repo.head()?.peel_to_commit()?;
+
+            commit_to_head(
+                &repo,
+                "cherry-pick",
+                &picked.author(),
+                &signature(repo_path)?,
+                &message,
+                &tree,
+                &[&head],
+            )?;
+            run_post_commit_hook(repo_path, event_sender);
+        }
+        RepositoryState::Revert | RepositoryState::RevertSequence => {
+            commit(repo_path, &message, None, Some("revert"), event_sender)?;
+        }
+        RepositoryState::Clean => {
+            return Err(anyhow::Error::msg(
+                "There is no merge, cherry-pick, revert or rebase to continue",
+            ))
+        }
+        state => {
+            return Err(anyhow::anyhow!(
+                "Continuing from {:?} is not supported",
+                state
+            ))
+        }
+    }
+
+    repo.cleanup_state()?;
+
+    Ok(())
+}
+
+/// Throws away a merge, cherry-pick or revert in progress, or restores the
+/// branch a rebase started from.
+pub fn abort_operation(repo_path: &Path) -> Result<()> {
+    let repo = repo(repo_path)?;
+
+    if is_interactive_rebase(repo_path)? {
+        return abort_rebase(repo_path);
+    }
+
+    match repo.state() {
+        state if is_rebase(state) => abort_rebase(repo_path)?,
+        RepositoryState::Merge
+        | RepositoryState::Revert
+        | RepositoryState::RevertSequence
+        | RepositoryState::CherryPick
+        | RepositoryState::CherryPickSequence => {
+            reset_merge(&repo)?;
+            repo.cleanup_state()?;
+        }
+        RepositoryState::Clean => {
+            return Err(anyhow::Error::msg("There is nothing to abort"));
+        }
+        state => {
+            return Err(anyhow::anyhow!(
+                "Aborting from {:?} is not supported",
+                state
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+/// Puts back the files an operation staged or left conflicted the way they
+/// are in HEAD, like `git reset --merge`. Other local changes are kept, and
+/// a staged file that was changed again since can't be reset without losing
+/// that change, so nothing is reset then.
+fn reset_merge(repo: &git2::Repository) -> Result<()> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(false);
+
+    let staged = Status::INDEX_NEW
+        | Status::INDEX_MODIFIED
+        | Status::INDEX_DELETED
+        | Status::INDEX_RENAMED
+        | Status::INDEX_TYPECHANGE;
+    let unstaged =
+        Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE;
+
+    let mut touched = Vec::new();
+    let mut changed = Vec::new();
+    for entry in repo.statuses(Some(&mut options))?.iter() {
+        let status = entry.status();
+        let path = match entry.path() {
+            Some(path) => path.to_string(),
+            None => continue,
+        };
+
+        if status.is_conflicted() {
+            touched.push(path);
+        } else if status.intersects(staged) {
+            if status.intersects(unstaged) {
+                changed.push(path.clone());
+            }
+            touched.push(path);
+        }
+    }
+
+    if !changed.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{} changed after being staged, stage or discard those changes first",
+            changed.join(", ")
+        ));
+    }
+    if touched.is_empty() {
+        return Ok(());
+    }
+
+    let head = repo.head()?.peel_to_commit()?;
+    repo.reset_default(Some(head.as_object()), touched.iter())?;
+
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force().remove_untracked(true);
+    for path in &touched {
+        checkout.path(path);
+    }
+    repo.checkout_head(Some(&mut checkout))?;
+
+    Ok(())
+}
+
+fn is_rebase(state: RepositoryState) -> bool {
+    matches!(
+        state,
+        RepositoryState::Rebase | RepositoryState::RebaseInteractive | RepositoryState::RebaseMerge
+    )
+}
@@ -60,6 +60,28 @@ pub fn get_file_status(repo_path: &Path) -> Result<Vec<FileStatus>> {
     Ok(files)
 }
 
+pub fn get_conflicted_paths(repo_path: &Path) -> Result<Vec<String>> {
+    let repo = repo(repo_path)?;
+    let mut paths = Vec::new();
+
+    for conflict in repo.index()?.conflicts()? {
+        let conflict = conflict?;
+        let entry = conflict.our.or(conflict.their).or(conflict.ancestor);
+
+        if let Some(entry) = entry {
+            paths.push(String::from_utf8_lossy(&entry.path).to_string());
+        }
+    }
+
+    Ok(paths)
+}
+
+pub fn is_untracked(repo_path: &Path, file_path: &str) -> Result<bool> {
+    let repo = repo(repo_path)?;
+    let status = repo.status_file(Path::new(file_path))?;
+    Ok(status.is_wt_new())
+}
+
 impl From<Status> for StatusType {
     fn from(status: Status) -> StatusType {
         if status.is_wt_new() || status.is_index_new() {
@@ -7,11 +7,11 @@ use num_traits::FromPrimitive;
 // Larger values are rough estimates, they don't need
 // to be super exact.
 const SECONDS_IN_MINTUE: u64 = 60;
-const SECONDS_IN_HOUR: u64 = 3600;
-const SECONDS_IN_DAY: u64 = 86400;
-const SECONDS_IN_WEEK: u64 = 604_800;
-const SECONDS_IN_MONTH: u64 = 2_419_200;
-const SECONDS_IN_YEAR: u64 = 31_536_000;
+pub const SECONDS_IN_HOUR: u64 = 3600;
+pub const SECONDS_IN_DAY: u64 = 86400;
+pub const SECONDS_IN_WEEK: u64 = 604_800;
+pub const SECONDS_IN_MONTH: u64 = 2_419_200;
+pub const SECONDS_IN_YEAR: u64 = 31_536_000;
 
 #[derive(Clone, Debug)]
 pub struct CommitDate {
@@ -22,10 +22,7 @@ pub struct CommitDate {
 impl CommitDate {
     pub fn new(git_time: GitTime) -> Self {
         let unix_time = git_time.seconds();
-        let date = match NaiveDateTime::from_timestamp_opt(unix_time, 0) {
-            Some(nd) => nd,
-            None => NaiveDateTime::default(),
-        };
+        let date = NaiveDateTime::from_timestamp_opt(unix_time, 0).unwrap_or_default();
         let time_since_commit = time_since_commit(unix_time as u64);
 
         Self {
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::Result;
+use git2::Signature;
+
+use crate::git::identity::{author_signature, signature};
+use crate::git::repo;
+
+pub const CO_AUTHORED_BY: &str = "Co-authored-by";
+pub const SIGNED_OFF_BY: &str = "Signed-off-by";
+
+/// The lines of a message that make up its trailer block: the last
+/// paragraph, when it isn't the summary and every line is `Key: value` or
+/// the indented continuation of one. Trailing comment lines are skipped.
+pub fn trailer_range(lines: &[String]) -> Option<Range<usize>> {
+    let end = lines
+        .iter()
+        .rposition(|line| !line.trim().is_empty() && !line.starts_with('#'))?
+        + 1;
+    let start = lines[..end]
+        .iter()
+        .rposition(|line| line.trim().is_empty())
+        .map_or(0, |blank| blank + 1);
+
+    if start == 0 {
+        return None;
+    }
+
+    let paragraph = &lines[start..end];
+    let is_trailers = is_trailer(&paragraph[0])
+        && paragraph
+            .iter()
+            .all(|line| is_trailer(line) || line.starts_with(char::is_whitespace));
+
+    is_trailers.then_some(start..end)
+}
+
+fn is_trailer(line: &str) -> bool {
+    match line.split_once(':') {
+        Some((key, _)) => {
+            !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        }
+        None => false,
+    }
+}
+
+/// Adds `key: value` to the end of the trailer block, starting one if the
+/// message has none. A trailer that's already there isn't repeated.
+pub fn add_trailer(lines: &mut Vec<String>, key: &str, value: &str) {
+    let trailer = format!("{}: {}", key, value);
+
+    match trailer_range(lines) {
+        Some(range) if lines[range.clone()].contains(&trailer) => {}
+        Some(range) => lines.insert(range.end, trailer),
+        None => {
+            // After the last line written, ahead of any comments
+            let end = lines
+                .iter()
+                .rposition(|line| !line.trim().is_empty() && !line.starts_with('#'))
+                .map_or(1, |last| last + 1);
+            if lines.len() < end {
+                lines.resize(end, String::new());
+            }
+            lines.splice(end..end, [String::new(), trailer]);
+        }
+    }
+}
+
+/// Removes `key: value` from the trailer block, and the blank line before
+/// the block if it was the only trailer. Returns false if it wasn't there.
+pub fn remove_trailer(lines: &mut Vec<String>, key: &str, value: &str) -> bool {
+    let trailer = format!("{}: {}", key, value);
+    let range = match trailer_range(lines) {
+        Some(range) => range,
+        None => return false,
+    };
+
+    match lines[range.clone()]
+        .iter()
+        .position(|line| *line == trailer)
+    {
+        Some(index) => {
+            lines.remove(range.start + index);
+            if range.len() == 1 {
+                lines.remove(range.start - 1);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// `Name <email>` of the committer, as git writes it in `Signed-off-by`.
+pub fn sign_off_identity(repo_path: &Path) -> Result<String> {
+    let signature = signature(repo_path)?;
+    Ok(identity_string(&signature))
+}
+
+/// Whether commits are signed off without asking, `buddy.signOff`.
+pub fn sign_off_by_default(repo_path: &Path) -> Result<bool> {
+    let config = repo(repo_path)?.config()?;
+    Ok(config.get_bool("buddy.signOff").unwrap_or(false))
+}
+
+/// Everyone who has authored a commit reachable from HEAD, as `Name
+/// <email>` after applying the mailmap, most frequent first. The current
+/// author is left out.
+pub fn repo_authors(repo_path: &Path) -> Result<Vec<String>> {
+    let repo = repo(repo_path)?;
+    let mailmap = repo.mailmap()?;
+    let own_email = author_signature(repo_path)
+        .ok()
+        .and_then(|signature| signature.email().map(|email| email.to_lowercase()));
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_head()?;
+
+    let mut authors: HashMap<String, (String, usize)> = HashMap::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        // Authors the mailmap can't read are skipped rather than failing
+        let author = match mailmap.resolve_signature(&commit.author()) {
+            Ok(author) => author,
+            Err(_) => continue,
+        };
+        let email = author.email().unwrap_or("").to_lowercase();
+
+        if email.is_empty() || Some(&email) == own_email.as_ref() {
+            continue;
+        }
+
+        authors
+            .entry(email)
+            .or_insert_with(|| (identity_string(&author), 0))
+            .1 += 1;
+    }
+
+    let mut authors = authors.into_values().collect::<Vec<_>>();
+    authors.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+
+    Ok(authors.into_iter().map(|(author, _)| author).collect())
+}
+
+fn identity_string(signature: &Signature) -> String {
+    format!(
+        "{} <{}>",
+        signature.name().unwrap_or(""),
+        signature.email().unwrap_or("")
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn summary_is_not_a_trailer_block() {
+        assert_eq!(trailer_range(&lines("Fix: the parser")), None);
+    }
+
+    #[test]
+    fn finds_last_paragraph() {
+        let message = lines(
+            "Add trailers\n\nBody text.\n\nSigned-off-by: A <a@b>\nCo-authored-by: B <b@c>\n# comment",
+        );
+
+        assert_eq!(trailer_range(&message), Some(4..6));
+    }
+
+    #[test]
+    fn body_is_not_a_trailer_block() {
+        let message = lines("Add trailers\n\nThis fixes: everything\nand more");
+
+        assert_eq!(trailer_range(&message), None);
+    }
+
+    #[test]
+    fn add_starts_and_extends_block() {
+        let mut message = lines("Add trailers\n\nBody text.\n# comment");
+
+        add_trailer(&mut message, CO_AUTHORED_BY, "B <b@c>");
+        add_trailer(&mut message, SIGNED_OFF_BY, "A <a@b>");
+        add_trailer(&mut message, SIGNED_OFF_BY, "A <a@b>");
+
+        assert_eq!(
+            message.join("\n"),
+            "Add trailers\n\nBody text.\n\nCo-authored-by: B <b@c>\nSigned-off-by: A <a@b>\n# comment"
+        );
+    }
+
+    #[test]
+    fn add_to_empty_message() {
+        let mut message = lines("");
+
+        add_trailer(&mut message, SIGNED_OFF_BY, "A <a@b>");
+
+        assert_eq!(message.join("\n"), "\n\nSigned-off-by: A <a@b>");
+    }
+
+    #[test]
+    fn remove_last_trailer_drops_block() {
+        let mut message = lines("Add trailers\n\nSigned-off-by: A <a@b>");
+
+        assert!(remove_trailer(&mut message, SIGNED_OFF_BY, "A <a@b>"));
+        assert!(!remove_trailer(&mut message, SIGNED_OFF_BY, "A <a@b>"));
+        assert_eq!(message.join("\n"), "Add trailers");
+    }
+}
@@ -51,6 +51,33 @@ impl ListWindow {
         Some(self.position)
     }
 
+    /// Moves the selected line, only shifting the window once the selection
+    /// leaves it.
+    pub fn move_cursor(&mut self, direction: ScrollDirection, distance: usize) {
+        let position = match direction {
+            ScrollDirection::Up => self.position.saturating_sub(distance),
+            ScrollDirection::Down => self.position + distance,
+        };
+        self.select(position);
+    }
+
+    pub fn select(&mut self, position: usize) {
+        if self.size == 0 {
+            self.reset();
+            return;
+        }
+
+        self.position = position.min(self.size - 1);
+
+        if self.position < self.min {
+            self.min = self.position;
+            self.max = self.min + self.height.saturating_sub(1);
+        } else if self.position > self.max {
+            self.max = self.position;
+            self.min = self.max.saturating_sub(self.height.saturating_sub(1));
+        }
+    }
+
     fn scroll_up(&mut self, distance: usize) {
         self.position = self.min;
         self.min = self.min.saturating_sub(distance);
@@ -196,6 +223,57 @@ mod test {
         assert_eq!(window.position, 99);
     }
 
+    #[test]
+    fn move_cursor_down_inside_window() {
+        let mut window = ListWindow::new(0, 9, 0, 20, 10);
+        window.move_cursor(ScrollDirection::Down, 1);
+
+        assert_eq!(window.min, 0);
+        assert_eq!(window.max, 9);
+        assert_eq!(window.position, 1);
+    }
+
+    #[test]
+    fn move_cursor_down_past_window() {
+        let mut window = ListWindow::new(0, 9, 9, 20, 10);
+        window.move_cursor(ScrollDirection::Down, 1);
+
+        assert_eq!(window.min, 1);
+        assert_eq!(window.max, 10);
+        assert_eq!(window.position, 10);
+    }
+
+    #[test]
+    fn move_cursor_down_bottom() {
+        let mut window = ListWindow::new(10, 19, 19, 20, 10);
+        window.move_cursor(ScrollDirection::Down, 5);
+
+        assert_eq!(window.min, 10);
+        assert_eq!(window.max, 19);
+        assert_eq!(window.position, 19);
+    }
+
+    #[test]
+    fn move_cursor_up_past_window() {
+        let mut window = ListWindow::new(5, 14, 5, 20, 10);
+        window.move_cursor(ScrollDirection::Up, 1);
+
+        assert_eq!(window.min, 4);
+        assert_eq!(window.max, 13);
+        assert_eq!(window.position, 4);
+    }
+
+    #[test]
+    fn select_after_shrinking() {
+        let mut window = ListWindow::new(10, 19, 19, 20, 10);
+        window.set_size(12);
+        window.select(19);
+
+        assert_eq!(window.min, 10);
+        assert_eq!(window.max, 19);
+        assert_eq!(window.position, 11);
+    }
+
     #[test]
     fn scroll_down_page_top() {
         let mut window = ListWindow::new(30, 54, 54, 100, 25);
@@ -23,6 +23,7 @@ mod component_style;
 mod components;
 mod git;
 mod list_window;
+mod text_area;
 mod ui;
 
 pub enum Event<I> {
@@ -0,0 +1,262 @@
+/// A multi-line text buffer with a cursor. Columns count characters, not
+/// bytes, so the cursor never lands inside a multi-byte character.
+pub struct TextArea {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+}
+
+impl TextArea {
+    pub fn new() -> Self {
+        Self {
+            lines: vec![String::new()],
+            row: 0,
+            col: 0,
+        }
+    }
+
+    /// Replaces the contents, leaving the cursor at the end of the first line.
+    pub fn set_text(&mut self, text: &str) {
+        self.lines = text.lines().map(|line| line.to_string()).collect();
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.row = 0;
+        self.col = self.line_len();
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    /// Moves the cursor, keeping it within the text.
+    pub fn set_cursor(&mut self, row: usize, col: usize) {
+        self.row = row.min(self.lines.len() - 1);
+        self.col = col.min(self.line_len());
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text("");
+    }
+
+    /// Replaces a line, keeping the cursor within it.
+    pub fn set_line(&mut self, row: usize, line: String) {
+        if let Some(current) = self.lines.get_mut(row) {
+            *current = line;
+            self.col = self.col.min(self.line_len());
+        }
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let index = self.byte_index(self.col);
+        self.lines[self.row].insert(index, c);
+        self.col += 1;
+    }
+
+    pub fn insert_newline(&mut self) {
+        let index = self.byte_index(self.col);
+        let rest = self.lines[self.row].split_off(index);
+        self.lines.insert(self.row + 1, rest);
+        self.row += 1;
+        self.col = 0;
+    }
+
+    /// Deletes the character before the cursor, joining lines at the start of
+    /// one.
+    pub fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let index = self.byte_index(self.col);
+            self.lines[self.row].remove(index);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len();
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.col < self.line_len() {
+            let index = self.byte_index(self.col);
+            self.lines[self.row].remove(index);
+        } else if self.row + 1 < self.lines.len() {
+            let line = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    /// Deletes back to the start of the previous word, like `ctrl-w` in a
+    /// shell.
+    pub fn delete_word(&mut self) {
+        if self.col == 0 {
+            self.backspace();
+            return;
+        }
+
+        let chars = self.lines[self.row].chars().collect::<Vec<_>>();
+        let mut start = self.col;
+        while start > 0 && chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+
+        let (from, to) = (self.byte_index(start), self.byte_index(self.col));
+        self.lines[self.row].replace_range(from..to, "");
+        self.col = start;
+    }
+
+    pub fn move_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_len();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if self.col < self.line_len() {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        if self.row > 0 {
+            self.row -= 1;
+            self.col = self.col.min(self.line_len());
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = self.col.min(self.line_len());
+        }
+    }
+
+    pub fn move_home(&mut self) {
+        self.col = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.col = self.line_len();
+    }
+
+    fn line_len(&self) -> usize {
+        self.lines[self.row].chars().count()
+    }
+
+    fn byte_index(&self, col: usize) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices()
+            .nth(col)
+            .map(|(index, _)| index)
+            .unwrap_or(line.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn text_area(text: &str) -> TextArea {
+        let mut text_area = TextArea::new();
+        for c in text.chars() {
+            match c {
+                '\n' => text_area.insert_newline(),
+                c => text_area.insert_char(c),
+            }
+        }
+        text_area
+    }
+
+    #[test]
+    fn insert_newline_splits_line() {
+        let mut text_area = text_area("summary body");
+        for _ in 0..4 {
+            text_area.move_left();
+        }
+        text_area.backspace();
+        text_area.insert_newline();
+
+        assert_eq!(text_area.text(), "summary\nbody");
+        assert_eq!(text_area.cursor(), (1, 0));
+    }
+
+    #[test]
+    fn backspace_joins_lines() {
+        let mut text_area = text_area("a\nb");
+        text_area.move_home();
+        text_area.backspace();
+
+        assert_eq!(text_area.text(), "ab");
+        assert_eq!(text_area.cursor(), (0, 1));
+    }
+
+    #[test]
+    fn delete_joins_lines() {
+        let mut text_area = text_area("a\nb");
+        text_area.move_up();
+        text_area.move_end();
+        text_area.delete();
+
+        assert_eq!(text_area.text(), "ab");
+        assert_eq!(text_area.cursor(), (0, 1));
+    }
+
+    #[test]
+    fn delete_word_skips_trailing_space() {
+        let mut text_area = text_area("fix the  bug  ");
+        text_area.delete_word();
+
+        assert_eq!(text_area.text(), "fix the  ");
+
+        text_area.delete_word();
+        assert_eq!(text_area.text(), "fix ");
+    }
+
+    #[test]
+    fn multi_byte_characters() {
+        let mut text_area = text_area("héllo");
+        text_area.move_left();
+        text_area.move_left();
+        text_area.move_left();
+        text_area.backspace();
+
+        assert_eq!(text_area.text(), "hllo");
+        assert_eq!(text_area.cursor(), (0, 1));
+    }
+
+    #[test]
+    fn move_down_clamps_column() {
+        let mut text_area = text_area("a long line\nshort");
+        text_area.move_up();
+        text_area.move_end();
+        text_area.move_down();
+
+        assert_eq!(text_area.cursor(), (1, 5));
+    }
+
+    #[test]
+    fn set_text_keeps_cursor_on_summary() {
+        let mut text_area = TextArea::new();
+        text_area.set_text("summary\n\nbody\n");
+
+        assert_eq!(text_area.lines().len(), 3);
+        assert_eq!(text_area.cursor(), (0, 7));
+    }
+}
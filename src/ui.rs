@@ -28,7 +28,8 @@ pub fn main_ui<B: Backend>(f: &mut Frame<B>, app: &mut App) -> Result<()> {
                 Constraint::Length(4),
                 Constraint::Length(8),
                 Constraint::Length(15),
-                Constraint::Length(8),
+                Constraint::Length(6),
+                Constraint::Min(8),
             ]
             .as_ref(),
         )
@@ -42,7 +43,8 @@ pub fn main_ui<B: Backend>(f: &mut Frame<B>, app: &mut App) -> Result<()> {
     app.status.draw(f, left_container[0])?;
     app.files.draw(f, left_container[1]);
     app.branches.draw(f, left_container[2])?;
-    app.logs.draw(f, left_container[3])?;
+    app.stash.draw(f, left_container[3])?;
+    app.logs.draw(f, left_container[4])?;
     app.diff.draw(f, right_container[0])?;
     app.diff_staged.draw(f, right_container[1])?;
 
@@ -111,16 +113,8 @@ pub fn prompt_new_repo<B: Backend>(
         match input_event {
             Event::Input(input) => {
                 match input.code {
-                    KeyCode::Char('j') => {
-                        if state.selected() == Some(0) {
-                            state.select(Some(1))
-                        }
-                    }
-                    KeyCode::Char('k') => {
-                        if state.selected() == Some(1) {
-                            state.select(Some(0))
-                        }
-                    }
+                    KeyCode::Char('j') if state.selected() == Some(0) => state.select(Some(1)),
+                    KeyCode::Char('k') if state.selected() == Some(1) => state.select(Some(0)),
                     KeyCode::Enter => {
                         if let Some(selection) = state.selected() {
                             if selection == 0 {